@@ -0,0 +1,11 @@
+#import bevy_sprite::mesh2d_vertex_output::VertexOutput
+
+@group(2) @binding(0) var<uniform> color: vec4<f32>;
+
+// a soft disc of `color`, fading out from the centre of the quad to its edge.
+@fragment
+fn fragment(mesh: VertexOutput) -> @location(0) vec4<f32> {
+    let distance = length(mesh.uv - vec2<f32>(0.5)) * 2.0;
+    let falloff = 1.0 - smoothstep(0.0, 1.0, distance);
+    return vec4<f32>(color.rgb, color.a * falloff);
+}
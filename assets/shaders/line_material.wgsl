@@ -1,19 +1,57 @@
+#import bevy_sprite::{
+    mesh2d_functions::{get_world_from_local, mesh2d_position_local_to_world, mesh2d_position_world_to_clip},
+    mesh2d_view_bindings::globals,
+}
+
+// see `LineFlow` in wire.rs.
+struct LineFlow {
+    intensity: f32,
+    flicker: f32,
+    speed: f32,
+    seed: f32,
+};
+
+@group(2) @binding(0) var<uniform> flow: LineFlow;
+
+// distance between the pulses travelling along a wire.
+const PULSE_SPACING: f32 = 12.0;
+
 struct Vertex {
+    @builtin(instance_index) instance_index: u32,
     @location(0) position: vec3<f32>,
-    @location(1) i_color: vec4<f32>,
+    @location(1) color: vec4<f32>,
+    // x is the distance along the wire from the end power flows out of.
+    @location(2) uv: vec2<f32>,
 };
 
 struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
     @location(0) color: vec4<f32>,
+    @location(1) distance: f32,
 };
 
 @vertex
 fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+    let world_from_local = get_world_from_local(vertex.instance_index);
+    let world_position = mesh2d_position_local_to_world(world_from_local, vec4<f32>(vertex.position, 1.0));
+    out.clip_position = mesh2d_position_world_to_clip(world_position);
     out.color = vertex.color;
+    out.distance = vertex.uv.x;
     return out;
 }
 
 @fragment
 fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
-    return in.color;
-}
\ No newline at end of file
+    // bright bands that travel away from the generator, only while power is flowing.
+    let phase = fract((in.distance - globals.time * flow.speed) / PULSE_SPACING);
+    let pulse = (1.0 - abs(phase * 2.0 - 1.0)) * select(0.0, 1.0, flow.speed > 0.0);
+    var brightness = mix(0.4, 1.0, flow.intensity) + flow.intensity * pulse * 0.6;
+
+    // drop out at random every so often, more the closer the generator is to overloading.
+    let frame = floor(globals.time * 20.0 + flow.seed);
+    let noise = fract(sin(frame * 12.9898) * 43758.5453);
+    brightness *= 1.0 - flow.flicker * step(0.5, noise) * 0.7;
+
+    return vec4<f32>(in.color.rgb * brightness, in.color.a);
+}
@@ -0,0 +1,50 @@
+// the command line, for each module to pick out the options it understands.
+pub struct Args(Vec<String>);
+
+impl Args {
+    pub fn from_env() -> Self {
+        Self(std::env::args().skip(1).collect())
+    }
+
+    // take `--<name>` out of the arguments, returning whether it was there.
+    pub fn flag(&mut self, name: &str) -> bool {
+        let Some(i) = self.position(name) else {
+            return false;
+        };
+        self.0.remove(i);
+        true
+    }
+
+    // take `--<name> <value>` out of the arguments, returning the value.
+    pub fn value(&mut self, name: &str) -> Option<String> {
+        let i = self.position(name)?;
+        self.0.remove(i);
+        if i < self.0.len() {
+            Some(self.0.remove(i))
+        } else {
+            eprintln!("--{name} needs a value, ignoring it");
+            None
+        }
+    }
+
+    // take `--<name> <value>` out of the arguments, and look up what the value names.
+    pub fn named<T>(&mut self, name: &str, from_name: impl Fn(&str) -> Option<T>) -> Option<T> {
+        let value = self.value(name)?;
+        let named = from_name(&value);
+        if named.is_none() {
+            eprintln!("no {name} called {value}, ignoring it");
+        }
+        named
+    }
+
+    // complain about any arguments nothing took.
+    pub fn finish(self) {
+        for arg in self.0 {
+            eprintln!("ignoring unknown argument {arg}");
+        }
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.0.iter().position(|arg| *arg == format!("--{name}"))
+    }
+}
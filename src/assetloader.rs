@@ -1,27 +1,146 @@
-use crate::server::ServerState;
+use crate::{manifest::ManifestKey, server::ServerState};
 use bevy::{prelude::*, utils::HashMap};
 
-#[derive(Copy, Clone, Eq, PartialEq, Hash, Reflect)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Reflect)]
 pub enum ImageKey {
     Server(ServerState),
     Power,
-    Tile
+    Switch,
+    Battery,
+    Cooler,
+    Tile,
+    // shown in place of any image that's missing.
+    Missing
+}
+
+impl HandleMap<ImageKey> {
+    // the image for `key`, falling back to the missing image if there isn't one.
+    pub fn get_or_missing(&self, key: &ImageKey) -> Handle<Image> {
+        self.get(key)
+            .or_else(|| self.get(&ImageKey::Missing))
+            .cloned()
+            .unwrap_or_default()
+    }
 }
 
 impl AssetKey for ImageKey {
     type Asset = Image;
 }
 
+impl ManifestKey for ImageKey {
+    const SECTION: &'static str = "images";
+
+    const NAMES: &'static [(&'static str, Self)] = &[
+        ("server.healthy", ImageKey::Server(ServerState::Healthy)),
+        ("server.hacked", ImageKey::Server(ServerState::Hacked)),
+        ("server.infected", ImageKey::Server(ServerState::Infected)),
+        ("server.broken", ImageKey::Server(ServerState::Broken)),
+        ("server.off", ImageKey::Server(ServerState::Off)),
+        ("power", ImageKey::Power),
+        ("switch", ImageKey::Switch),
+        ("battery", ImageKey::Battery),
+        ("cooler", ImageKey::Cooler),
+        ("tile", ImageKey::Tile),
+        ("missing", ImageKey::Missing),
+    ];
+}
+
+// image paths come from `assets/assets.manifest`, so this starts out empty.
 impl FromWorld for HandleMap<ImageKey> {
+    fn from_world(_world: &mut World) -> Self {
+        HashMap::default().into()
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Reflect)]
+pub enum SfxKey {
+    Submit,
+    Error,
+    HackProgress,
+    HackDone,
+    WireCut,
+    Alarm,
+    Win,
+    Loss,
+    // looped for as long as the game is running.
+    Ambient
+}
+
+impl AssetKey for SfxKey {
+    type Asset = AudioSource;
+}
+
+impl ManifestKey for SfxKey {
+    const SECTION: &'static str = "sounds";
+
+    const NAMES: &'static [(&'static str, Self)] = &[
+        ("submit", SfxKey::Submit),
+        ("error", SfxKey::Error),
+        ("hack.progress", SfxKey::HackProgress),
+        ("hack.done", SfxKey::HackDone),
+        ("wire.cut", SfxKey::WireCut),
+        ("alarm", SfxKey::Alarm),
+        ("win", SfxKey::Win),
+        ("loss", SfxKey::Loss),
+        ("ambient", SfxKey::Ambient),
+    ];
+}
+
+// sound paths come from `assets/assets.manifest`, so this starts out empty.
+impl FromWorld for HandleMap<SfxKey> {
+    fn from_world(_world: &mut World) -> Self {
+        HashMap::default().into()
+    }
+}
+
+// server images are sprite sheets, with frames laid out left to right.
+pub const SERVER_FRAME_SIZE: u32 = 32;
+
+// how many frames are in each server state's sprite sheet.
+pub fn server_frames(state: ServerState) -> u32 {
+    match state {
+        ServerState::Healthy => 2,
+        ServerState::Hacked => 2,
+        ServerState::Infected => 4,
+        ServerState::Broken => 4,
+        ServerState::Off => 1,
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Reflect)]
+pub enum AtlasKey {
+    Server(ServerState),
+    // a single frame, for images that aren't sprite sheets.
+    Single
+}
+
+impl AssetKey for AtlasKey {
+    type Asset = TextureAtlasLayout;
+}
+
+impl FromWorld for HandleMap<AtlasKey> {
     fn from_world(world: &mut World) -> Self {
-        let asset_server = world.resource::<AssetServer>();
+        let mut layouts = world.resource_mut::<Assets<TextureAtlasLayout>>();
+        let mut sheet = |frames| {
+            layouts.add(TextureAtlasLayout::from_grid(
+                UVec2::splat(SERVER_FRAME_SIZE),
+                frames,
+                1,
+                None,
+                None,
+            ))
+        };
         [
-            (ImageKey::Server(ServerState::Healthy), asset_server.load("images/server/healthy.png")),
-            (ImageKey::Server(ServerState::Hacked), asset_server.load("images/server/hacked.png")),
-            (ImageKey::Server(ServerState::Infected), asset_server.load("images/server/infected.png")),
-            (ImageKey::Power, asset_server.load("images/power.png")),
-            (ImageKey::Tile, asset_server.load("images/tile.png"))
+            ServerState::Healthy,
+            ServerState::Hacked,
+            ServerState::Infected,
+            ServerState::Broken,
+            ServerState::Off,
         ]
+        .map(|state| (AtlasKey::Server(state), sheet(server_frames(state))))
+        .into_iter()
+        .chain([(AtlasKey::Single, sheet(1))])
+        .collect::<HashMap<_, _>>()
         .into()
     }
 }
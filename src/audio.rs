@@ -0,0 +1,228 @@
+use bevy::{
+    audio::{SpatialScale, Volume},
+    prelude::*,
+    utils::HashSet,
+};
+use bevy_simple_text_input::TextInputSubmitEvent;
+
+use crate::{
+    assetloader::{HandleMap, SfxKey},
+    hack::{HackFinished, HackMilestone},
+    loading::GameState,
+    power::GeneratorOverheated,
+    rendering::{setup_camera, InGameCamera, RES_WIDTH},
+    server::Server,
+    text_input::{Action, Rejected},
+    thermal::ServerOverheated,
+    virus::Outcome,
+    wire::{UpdateWire, Wire, WireOperation},
+};
+
+// scales world positions so that a sound at the edge of the screen is panned fully to one side.
+pub const SPATIAL_SCALE: SpatialScale = SpatialScale::new_2d(2.0 / RES_WIDTH as f32);
+
+// the ambient loop plays quieter than everything else.
+const AMBIENT_VOLUME: f32 = 0.4;
+
+// fraction of `overheat` at which a server starts sounding the alarm.
+const ALARM_HEAT: f32 = 0.8;
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<SoundSettings>()
+        .add_systems(Startup, add_listener.after(setup_camera))
+        .add_systems(OnEnter(GameState::Playing), start_ambient)
+        .add_systems(
+            Update,
+            (
+                set_volume,
+                play_command_sounds,
+                play_network_sounds,
+                play_outcome_sounds,
+            ),
+        );
+}
+
+#[derive(Resource)]
+pub struct SoundSettings {
+    // between 0 and 1.
+    pub volume: f32,
+}
+
+impl Default for SoundSettings {
+    fn default() -> Self {
+        Self { volume: 0.5 }
+    }
+}
+
+#[derive(Component)]
+struct Ambient;
+
+// the camera hears everything, so sounds pan with where they are on screen.
+fn add_listener(mut commands: Commands, camera: Query<Entity, With<InGameCamera>>) {
+    commands
+        .entity(camera.single())
+        .insert(SpatialListener::new(RES_WIDTH as f32));
+}
+
+fn start_ambient(
+    mut commands: Commands,
+    sounds: Res<HandleMap<SfxKey>>,
+    settings: Res<SoundSettings>,
+) {
+    let Some(source) = sounds.get(&SfxKey::Ambient) else {
+        return;
+    };
+    commands.spawn((
+        AudioBundle {
+            source: source.clone(),
+            settings: PlaybackSettings::LOOP
+                .with_volume(Volume::new(settings.volume * AMBIENT_VOLUME)),
+        },
+        Ambient,
+        StateScoped(GameState::Playing),
+    ));
+}
+
+// run `volume <0-100>` commands.
+fn set_volume(
+    mut actions: EventReader<Action>,
+    mut rejected: EventWriter<Rejected>,
+    mut settings: ResMut<SoundSettings>,
+    ambient: Query<&AudioSink, With<Ambient>>,
+) {
+    for action in actions.read() {
+        let Action::Volume { percent } = action else {
+            continue;
+        };
+
+        if *percent > 100 {
+            rejected.send(Rejected("volume has to be between 0 and 100".to_string()));
+            continue;
+        }
+
+        settings.volume = *percent as f32 / 100.0;
+        for sink in &ambient {
+            sink.set_volume(settings.volume * AMBIENT_VOLUME);
+        }
+    }
+}
+
+// play a sound once, panned to `position` if it has one.
+fn play(
+    commands: &mut Commands,
+    sounds: &HandleMap<SfxKey>,
+    settings: &SoundSettings,
+    key: SfxKey,
+    position: Option<Vec3>,
+) {
+    let Some(source) = sounds.get(&key) else {
+        return;
+    };
+    let playback = PlaybackSettings::DESPAWN.with_volume(Volume::new(settings.volume));
+    let mut sound = commands.spawn(AudioBundle {
+        source: source.clone(),
+        settings: playback.with_spatial(position.is_some()),
+    });
+    if let Some(position) = position {
+        sound.insert(TransformBundle::from_transform(Transform::from_translation(
+            position,
+        )));
+    }
+}
+
+fn play_command_sounds(
+    mut commands: Commands,
+    mut submitted: EventReader<TextInputSubmitEvent>,
+    mut rejected: EventReader<Rejected>,
+    sounds: Res<HandleMap<SfxKey>>,
+    settings: Res<SoundSettings>,
+) {
+    if rejected.read().count() > 0 {
+        play(&mut commands, &sounds, &settings, SfxKey::Error, None);
+    } else if submitted.read().count() > 0 {
+        play(&mut commands, &sounds, &settings, SfxKey::Submit, None);
+    }
+    submitted.clear();
+}
+
+// sounds that come from somewhere in the network, panned to where they happened.
+fn play_network_sounds(
+    mut commands: Commands,
+    mut ev_milestone: EventReader<HackMilestone>,
+    mut ev_finished: EventReader<HackFinished>,
+    mut ev_wire: EventReader<UpdateWire>,
+    mut ev_server_overheated: EventReader<ServerOverheated>,
+    mut ev_generator_overheated: EventReader<GeneratorOverheated>,
+    servers: Query<(Entity, &Server)>,
+    wires: Query<&Wire>,
+    transforms: Query<&Transform>,
+    sounds: Res<HandleMap<SfxKey>>,
+    settings: Res<SoundSettings>,
+    mut alarmed: Local<HashSet<Entity>>,
+) {
+    // wires are meshes drawn from the origin, so cuts are placed along them instead.
+    for ev in ev_wire.read() {
+        if matches!(ev.operation, WireOperation::Cut) {
+            let position = cut_position(&wires, &transforms, ev.wire, ev.pos);
+            play(&mut commands, &sounds, &settings, SfxKey::WireCut, position);
+        }
+    }
+
+    let mut play_at = |key: SfxKey, entity: Entity| {
+        let position = transforms.get(entity).ok().map(|t| t.translation);
+        play(&mut commands, &sounds, &settings, key, position);
+    };
+
+    for ev in ev_milestone.read() {
+        play_at(SfxKey::HackProgress, ev.target);
+    }
+    for ev in ev_finished.read() {
+        play_at(SfxKey::HackDone, ev.target);
+    }
+    for ev in ev_server_overheated.read() {
+        play_at(SfxKey::Alarm, ev.server);
+    }
+    for ev in ev_generator_overheated.read() {
+        play_at(SfxKey::Alarm, ev.generator);
+    }
+
+    // warn once as a server gets close to overheating, and again if it cools down and heats
+    // back up.
+    for (entity, server) in &servers {
+        if server.temp > server.overheat * ALARM_HEAT {
+            if alarmed.insert(entity) {
+                play_at(SfxKey::Alarm, entity);
+            }
+        } else {
+            alarmed.remove(&entity);
+        }
+    }
+}
+
+// where along a wire the cut at segment `pos` is.
+fn cut_position(
+    wires: &Query<&Wire>,
+    transforms: &Query<&Transform>,
+    wire: Entity,
+    pos: usize,
+) -> Option<Vec3> {
+    let wire = wires.get(wire).ok()?;
+    let ends = transforms.get_many(wire.terminals).ok()?;
+    let points = wire.points(ends.map(|transform| transform.translation.truncate()));
+    points.get(pos).or(points.last()).map(|point| point.extend(0.0))
+}
+
+fn play_outcome_sounds(
+    mut commands: Commands,
+    mut ev_outcome: EventReader<Outcome>,
+    sounds: Res<HandleMap<SfxKey>>,
+    settings: Res<SoundSettings>,
+) {
+    for outcome in ev_outcome.read() {
+        let key = match outcome {
+            Outcome::Won => SfxKey::Win,
+            Outcome::Lost => SfxKey::Loss,
+        };
+        play(&mut commands, &sounds, &settings, key, None);
+    }
+}
@@ -0,0 +1,220 @@
+use bevy::{ecs::schedule::ScheduleLabel, prelude::*};
+
+use crate::{
+    level::{LevelConfig, LevelMode},
+    loading::GameState,
+    text_input::{Action, Rejected},
+};
+
+// how many ticks a command advances the simulation by in turn-based mode.
+pub const TICKS_PER_TURN: usize = 64;
+
+// the fastest the real-time clock can be sped up to, in ticks per fixed timestep.
+const MAX_SPEED: usize = 8;
+
+const TEXT_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
+const BACKGROUND_COLOR: Color = Color::srgb(0.15, 0.15, 0.15);
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_schedule(Tick)
+        .init_resource::<Clock>()
+        .add_systems(Startup, setup_clock_label)
+        .add_systems(OnEnter(GameState::Playing), start_clock)
+        .add_systems(Update, (control_clock, update_clock_label).chain())
+        .add_systems(PostUpdate, advance_turns)
+        .add_systems(FixedUpdate, accrue_ticks.run_if(in_state(GameState::Playing)))
+        .add_systems(PreUpdate, run_ticks.run_if(in_state(GameState::Playing)));
+}
+
+// one step of the simulation. everything that changes the network over time
+// (programs running on servers, power, heat, the defender) runs here rather than
+// on a frame or fixed timestep, so that it can be driven by either clock mode.
+// ticks are run at the start of a frame, before any commands are carried out, so that
+// commands always land between the same two ticks when a game is replayed.
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Tick;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ClockMode {
+    // ticks run on bevy's fixed timestep.
+    RealTime,
+    // ticks only run when a command is submitted.
+    TurnBased,
+}
+
+#[derive(Resource)]
+pub struct Clock {
+    pub mode: ClockMode,
+    pub paused: bool,
+    // ticks run per fixed timestep in real-time mode.
+    pub speed: usize,
+    // ticks waiting to be run. turn-based mode queues a turn's worth for every command, and
+    // real-time mode queues `speed` every fixed timestep.
+    pub pending: usize,
+    // while a replay is playing back, it runs the ticks itself and nothing is queued.
+    pub playback: bool,
+    // ticks run since the level started.
+    pub elapsed: usize,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self {
+            mode: ClockMode::RealTime,
+            paused: false,
+            speed: 1,
+            pending: 0,
+            playback: false,
+            elapsed: 0,
+        }
+    }
+}
+
+// puzzle levels are played a turn at a time, and challenge levels against the clock, until
+// the player picks otherwise with `mode`.
+fn start_clock(config: Res<LevelConfig>, mut clock: ResMut<Clock>) {
+    clock.mode = match config.mode {
+        LevelMode::Puzzle => ClockMode::TurnBased,
+        LevelMode::Challenge => ClockMode::RealTime,
+    };
+    clock.pending = 0;
+}
+
+// run `pause`, `speed <n>` and `mode <realtime|turns>` commands.
+fn control_clock(
+    mut actions: EventReader<Action>,
+    mut rejected: EventWriter<Rejected>,
+    mut clock: ResMut<Clock>,
+) {
+    for action in actions.read() {
+        match action {
+            Action::Pause => {
+                clock.paused = !clock.paused;
+            }
+            Action::Speed { speed } => {
+                if (1..=MAX_SPEED).contains(speed) {
+                    clock.speed = *speed;
+                } else {
+                    rejected.send(Rejected(format!(
+                        "speed has to be between 1 and {MAX_SPEED}"
+                    )));
+                }
+            }
+            Action::Mode { mode } => {
+                clock.mode = *mode;
+                clock.pending = 0;
+            }
+            _ => {}
+        }
+    }
+}
+
+// whether a command acts on the network, and so takes a turn in turn-based mode.
+fn takes_turn(action: &Action) -> bool {
+    matches!(
+        action,
+        Action::Wait
+            | Action::Move { .. }
+            | Action::Fork { .. }
+            | Action::Hack { .. }
+            | Action::Reboot { .. }
+            | Action::Cycle { .. }
+            | Action::Heat { .. }
+            | Action::Cut { .. }
+    )
+}
+
+// queue up a turn for every command that acted on the network this frame, once they've all
+// been carried out. commands that were rejected leave the network as it was, so they don't
+// take a turn. commands come in one at a time, so any rejection is for the command that
+// came with it.
+fn advance_turns(
+    mut actions: EventReader<Action>,
+    mut rejected: EventReader<Rejected>,
+    mut clock: ResMut<Clock>,
+) {
+    let acted = actions.read().filter(|action| takes_turn(action)).count();
+    let rejected = rejected.read().count();
+    if clock.mode == ClockMode::TurnBased {
+        clock.pending += acted.saturating_sub(rejected) * TICKS_PER_TURN;
+    }
+}
+
+// whether the game is being played, rather than played back.
+pub fn live(clock: Res<Clock>) -> bool {
+    !clock.playback
+}
+
+// queue up ticks on the fixed timestep in real-time mode.
+fn accrue_ticks(mut clock: ResMut<Clock>) {
+    if clock.mode == ClockMode::RealTime && !clock.paused {
+        clock.pending += clock.speed;
+    }
+}
+
+// run every tick that's been queued up.
+pub fn run_ticks(world: &mut World) {
+    let ticks = {
+        let mut clock = world.resource_mut::<Clock>();
+        let pending = std::mem::take(&mut clock.pending);
+        if clock.playback {
+            0
+        } else {
+            pending
+        }
+    };
+
+    for _ in 0..ticks {
+        tick(world);
+    }
+}
+
+// advance the simulation by one tick.
+pub fn tick(world: &mut World) {
+    world.run_schedule(Tick);
+    world.resource_mut::<Clock>().elapsed += 1;
+}
+
+#[derive(Component)]
+struct ClockLabel;
+
+fn setup_clock_label(mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                right: Val::Px(20.0),
+                bottom: Val::Px(20.0),
+                padding: UiRect::all(Val::Px(4.0)),
+                ..default()
+            },
+            background_color: BACKGROUND_COLOR.into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 16.,
+                        color: TEXT_COLOR,
+                        ..default()
+                    },
+                ),
+                ClockLabel,
+            ));
+        });
+}
+
+fn update_clock_label(clock: Res<Clock>, mut label: Query<&mut Text, With<ClockLabel>>) {
+    if !clock.is_changed() {
+        return;
+    }
+
+    let status = match clock.mode {
+        ClockMode::RealTime if clock.paused => "paused".to_string(),
+        ClockMode::RealTime => format!("real-time x{}", clock.speed),
+        ClockMode::TurnBased => "turn-based".to_string(),
+    };
+    label.single_mut().sections[0].value = format!("tick {} - {}", clock.elapsed, status);
+}
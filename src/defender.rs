@@ -0,0 +1,95 @@
+use bevy::prelude::*;
+use rand::seq::IteratorRandom;
+
+use crate::{
+    clock::Tick,
+    level::LevelRng,
+    reboot::{RebootStarted, REBOOT_TICKS},
+    server::Server,
+    upgrades::{Upgrade, Upgrades},
+};
+
+// how long the defender spends looking at a server before deciding whether to reboot it.
+const INVESTIGATION_TICKS: usize = 640;
+
+// how long the defender waits between investigations.
+const IDLE_TICKS: usize = 320;
+
+// servers hotter than this fraction of their `overheat` stand out to the defender.
+const NOTICED_HEAT: f32 = 0.5;
+
+// each level of the heat upgrade lets servers get this much hotter before they're noticed.
+const UPGRADE_STEALTH: f32 = 0.2;
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<Defender>()
+        .add_systems(Tick, (distract, investigate).chain());
+}
+
+// the network's sysadmin. it looks over servers one at a time, and reboots
+// any it finds the virus on.
+#[derive(Resource, Default, Clone, PartialEq)]
+pub struct Defender {
+    pub target: Option<Entity>,
+    pub elapsed: usize,
+}
+
+// a reboot the defender didn't ask for looks like an incident, so it goes to look at that instead.
+fn distract(mut defender: ResMut<Defender>, mut ev_started: EventReader<RebootStarted>) {
+    for ev in ev_started.read() {
+        if ev.by_virus {
+            defender.target = Some(ev.server);
+            defender.elapsed = 0;
+        }
+    }
+}
+
+// pick a server to look at, going for the hottest one that stands out if there is one.
+fn investigate(
+    mut defender: ResMut<Defender>,
+    mut servers: Query<(Entity, &mut Server)>,
+    mut ev_started: EventWriter<RebootStarted>,
+    upgrades: Res<Upgrades>,
+    mut rng: ResMut<LevelRng>,
+) {
+    defender.elapsed += 1;
+
+    let Some(target) = defender.target else {
+        if defender.elapsed >= IDLE_TICKS {
+            let noticed = NOTICED_HEAT + UPGRADE_STEALTH * upgrades.level(Upgrade::Heat) as f32;
+            let hottest = servers
+                .iter()
+                .filter(|(_, server)| server.controlled())
+                .filter(|(_, server)| server.temp > server.overheat * noticed)
+                .max_by(|(_, a), (_, b)| a.temp.total_cmp(&b.temp))
+                .map(|(entity, _)| entity);
+
+            defender.target = hottest.or_else(|| {
+                servers
+                    .iter()
+                    .filter(|(_, server)| server.controlled())
+                    .map(|(entity, _)| entity)
+                    .choose(&mut rng.0)
+            });
+            defender.elapsed = 0;
+        }
+        return;
+    };
+
+    if defender.elapsed < INVESTIGATION_TICKS {
+        return;
+    }
+
+    if let Ok((_, mut server)) = servers.get_mut(target) {
+        if server.controlled() {
+            let virus_killed = server.reboot(REBOOT_TICKS);
+            ev_started.send(RebootStarted {
+                server: target,
+                by_virus: false,
+                virus_killed,
+            });
+        }
+    }
+    defender.target = None;
+    defender.elapsed = 0;
+}
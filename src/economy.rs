@@ -0,0 +1,128 @@
+use bevy::prelude::*;
+
+use crate::{
+    clock::{Clock, ClockMode, Tick, TICKS_PER_TURN},
+    server::Server,
+    text_input::Action,
+};
+
+// compute a controlled server makes each tick, per unit of power it draws.
+const COMPUTE_PER_POWER: f32 = 0.01;
+
+const TEXT_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
+const BACKGROUND_COLOR: Color = Color::srgb(0.15, 0.15, 0.15);
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<Compute>()
+        .init_resource::<Income>()
+        .add_systems(Startup, setup_hud)
+        .add_systems(Tick, harvest)
+        .add_systems(Update, update_hud);
+}
+
+// the virus' currency. commands and upgrades are paid for with it.
+#[derive(Resource, Default)]
+pub struct Compute(pub f32);
+
+impl Compute {
+    // take `amount` out of the balance if there's enough of it, returning whether there was.
+    pub fn spend(&mut self, amount: f32) -> bool {
+        if self.0 < amount {
+            return false;
+        }
+        self.0 -= amount;
+        true
+    }
+}
+
+// compute made over the last tick.
+#[derive(Resource, Default)]
+pub struct Income(pub f32);
+
+// compute it takes to carry out an action.
+pub fn cost(action: &Action) -> f32 {
+    match action {
+        Action::Hack { .. } => 10.0,
+        Action::Cycle { .. } => 5.0,
+        Action::Heat { .. } => 5.0,
+        Action::Cut { .. } => 5.0,
+        Action::Fork { .. } => 30.0,
+        Action::Move { .. }
+        | Action::Reboot { .. }
+        | Action::Upgrade { .. }
+        | Action::Wait
+        | Action::Pause
+        | Action::Speed { .. }
+        | Action::Mode { .. }
+        | Action::Inspect { .. }
+        | Action::Volume { .. }
+        | Action::Heatmap
+        | Action::Undo
+        | Action::Redo
+        | Action::Hint => 0.0,
+    }
+}
+
+// every server the virus controls makes compute, in proportion to the power it draws.
+// hot servers throttle, making less the closer they get to overheating.
+fn harvest(servers: Query<&Server>, mut compute: ResMut<Compute>, mut income: ResMut<Income>) {
+    income.0 = servers
+        .iter()
+        .filter(|server| server.controlled())
+        .map(|server| {
+            let throttle = 1.0 - (server.temp / server.overheat).clamp(0.0, 1.0);
+            server.power * COMPUTE_PER_POWER * throttle
+        })
+        .sum();
+    compute.0 += income.0;
+}
+
+#[derive(Component)]
+struct ComputeCounter;
+
+fn setup_hud(mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                left: Val::Px(20.0),
+                top: Val::Px(20.0),
+                padding: UiRect::all(Val::Px(4.0)),
+                ..default()
+            },
+            background_color: BACKGROUND_COLOR.into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 16.,
+                        color: TEXT_COLOR,
+                        ..default()
+                    },
+                ),
+                ComputeCounter,
+            ));
+        });
+}
+
+fn update_hud(
+    compute: Res<Compute>,
+    income: Res<Income>,
+    clock: Res<Clock>,
+    time: Res<Time<Fixed>>,
+    mut counter: Query<&mut Text, With<ComputeCounter>>,
+) {
+    // show income per second in real-time mode, and per turn in turn-based mode.
+    let (rate, unit) = match clock.mode {
+        ClockMode::RealTime => (
+            income.0 * clock.speed as f32 / time.timestep().as_secs_f32(),
+            "s",
+        ),
+        ClockMode::TurnBased => (income.0 * TICKS_PER_TURN as f32, "turn"),
+    };
+    counter.single_mut().sections[0].value =
+        format!("compute: {:.0} (+{:.1}/{})", compute.0, rate, unit);
+}
@@ -0,0 +1,57 @@
+use bevy::prelude::*;
+
+use crate::{
+    clock::Tick,
+    hack::progress_hacks,
+    server::{Server, ServerState, Switch},
+    wire::{LineMaterial, Wire},
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(Update, dim_firewalls)
+        .add_systems(Tick, update_firewalls.before(progress_hacks));
+}
+
+// stops hacks and the virus from crossing the wire this is attached to, for as long as the
+// node hosting it keeps running. overheating or power-cycling the host takes the firewall down.
+#[derive(Component)]
+pub struct Firewall {
+    pub host: Entity,
+    pub up: bool,
+}
+
+// whether hacks and the virus can cross a wire with this firewall, if it has one.
+pub fn passable(firewall: Option<&Firewall>) -> bool {
+    !firewall.is_some_and(|firewall| firewall.up)
+}
+
+// raise or drop every firewall with its host. this runs on the tick, before hacks progress,
+// so a firewall comes down on the same tick as its host does, however fast time is running.
+fn update_firewalls(
+    mut firewalls: Query<&mut Firewall>,
+    servers: Query<&Server>,
+    switches: Query<&Switch>,
+) {
+    for mut firewall in &mut firewalls {
+        let up = match (servers.get(firewall.host), switches.get(firewall.host)) {
+            (Ok(server), _) => !matches!(server.state, ServerState::Off | ServerState::Broken),
+            (_, Ok(switch)) => switch.on(),
+            _ => false,
+        };
+        if firewall.up != up {
+            firewall.up = up;
+        }
+    }
+}
+
+// dim the wires of firewalls that are down.
+fn dim_firewalls(
+    firewalls: Query<(&Firewall, &Handle<LineMaterial>), (With<Wire>, Changed<Firewall>)>,
+    mut materials: ResMut<Assets<LineMaterial>>,
+) {
+    for (firewall, handle) in &firewalls {
+        if let Some(material) = materials.get_mut(handle) {
+            material.flow.intensity = if firewall.up { 1.0 } else { 0.0 };
+        }
+    }
+}
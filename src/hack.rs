@@ -0,0 +1,241 @@
+use bevy::{
+    color::palettes::css::{DARK_GRAY, LIME},
+    prelude::*,
+};
+
+use crate::{
+    clock::Tick,
+    economy::{cost, Compute},
+    firewall::{passable, Firewall},
+    notices::Notice,
+    server::{find_server, name_of, relay, Server, ServerProgram, ServerState, Switch},
+    text_input::{Action, Rejected},
+    upgrades::{Upgrade, Upgrades},
+    wire::{connected, Wire},
+};
+
+// ticks of hacking needed for each point of the target's defence.
+const TICKS_PER_DEFENCE: f32 = 64.0;
+
+// each level of the hack upgrade multiplies the time a hack takes by this.
+const UPGRADE_SPEEDUP: f32 = 0.75;
+
+const BAR_WIDTH: f32 = 24.0;
+
+// how far above the target's centre the progress bar is drawn.
+const BAR_OFFSET: f32 = 20.0;
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_event::<HackFinished>()
+        .add_event::<HackMilestone>()
+        .add_event::<HackInterrupted>()
+        .add_systems(Update, (start_hacks, draw_hack_progress, report_hacks))
+        .add_systems(Tick, progress_hacks);
+}
+
+// a hack being run by the server this is attached to.
+#[derive(Component)]
+pub struct HackProgress {
+    pub target: Entity,
+    pub elapsed: usize,
+    pub required: usize,
+}
+
+impl HackProgress {
+    pub fn fraction(&self) -> f32 {
+        self.elapsed as f32 / self.required as f32
+    }
+}
+
+#[derive(Event)]
+pub struct HackFinished {
+    pub source: Entity,
+    pub target: Entity,
+}
+
+// sent each time a hack on `target` gets another quarter of the way done.
+#[derive(Event)]
+pub struct HackMilestone {
+    pub target: Entity,
+}
+
+#[derive(Event)]
+pub struct HackInterrupted {
+    pub source: Entity,
+    pub target: Entity,
+}
+
+// start running `msg <source> hack <target>` commands.
+fn start_hacks(
+    mut commands: Commands,
+    mut actions: EventReader<Action>,
+    mut rejected: EventWriter<Rejected>,
+    mut servers: Query<(Entity, &mut Server)>,
+    wires: Query<(&Wire, Option<&Firewall>)>,
+    switches: Query<&Switch>,
+    upgrades: Res<Upgrades>,
+    mut compute: ResMut<Compute>,
+) {
+    for action in actions.read() {
+        let Action::Hack { source, target } = action else {
+            continue;
+        };
+
+        let (Some(source_entity), Some(target_entity)) =
+            (find_server(&servers, source), find_server(&servers, target))
+        else {
+            rejected.send(Rejected(format!("no server named {source} or {target}")));
+            continue;
+        };
+
+        let Ok([(_, source_server), (_, target_server)]) =
+            servers.get_many([source_entity, target_entity])
+        else {
+            continue;
+        };
+
+        if !source_server.controlled() {
+            rejected.send(Rejected(format!("{source} isn't under the virus' control")));
+            continue;
+        }
+
+        // a server runs one program at a time, and starting another would throw this one away.
+        if source_server.program != ServerProgram::Wait {
+            rejected.send(Rejected(format!("{source} is busy")));
+            continue;
+        }
+
+        if target_server.state != ServerState::Healthy {
+            rejected.send(Rejected(format!("{target} can't be hacked right now")));
+            continue;
+        }
+
+        let relays = |e| relay(&switches, e);
+        if !connected(wires.iter().map(|(wire, _)| wire), relays, source_entity, target_entity) {
+            rejected.send(Rejected(format!("{source} isn't wired to {target}")));
+            continue;
+        }
+
+        let open = wires
+            .iter()
+            .filter(|(_, firewall)| passable(*firewall))
+            .map(|(wire, _)| wire);
+        if !connected(open, relays, source_entity, target_entity) {
+            rejected.send(Rejected(format!("a firewall is in the way of {target}")));
+            continue;
+        }
+
+        if !compute.spend(cost(action)) {
+            rejected.send(Rejected(format!("not enough compute to run that on {source}")));
+            continue;
+        }
+
+        let speedup = UPGRADE_SPEEDUP.powi(upgrades.level(Upgrade::Hack) as i32);
+        let required = (target_server.defence * TICKS_PER_DEFENCE * speedup)
+            .ceil()
+            .max(1.0) as usize;
+        if let Ok((_, mut source_server)) = servers.get_mut(source_entity) {
+            source_server.program = ServerProgram::Hack(target_entity);
+        }
+        commands.entity(source_entity).insert(HackProgress {
+            target: target_entity,
+            elapsed: 0,
+            required,
+        });
+    }
+}
+
+// advance every running hack by a tick, finishing or interrupting them as needed.
+pub fn progress_hacks(
+    mut commands: Commands,
+    mut hacks: Query<(Entity, &mut HackProgress)>,
+    mut servers: Query<&mut Server>,
+    wires: Query<(&Wire, Option<&Firewall>)>,
+    switches: Query<&Switch>,
+    mut ev_finished: EventWriter<HackFinished>,
+    mut ev_milestone: EventWriter<HackMilestone>,
+    mut ev_interrupted: EventWriter<HackInterrupted>,
+) {
+    let open = wires
+        .iter()
+        .filter(|(_, firewall)| passable(*firewall))
+        .map(|(wire, _)| wire)
+        .collect::<Vec<_>>();
+    for (source, mut hack) in &mut hacks {
+        let target = hack.target;
+
+        // a hack needs both ends up and running, and a live line between them.
+        // a reboot or power loss takes a server off, which stops the hack.
+        let running = match servers.get_many([source, target]) {
+            Ok([source_server, target_server]) => {
+                source_server.controlled()
+                    && source_server.program == ServerProgram::Hack(target)
+                    && target_server.state == ServerState::Healthy
+                    && connected(open.iter().copied(), |e| relay(&switches, e), source, target)
+            }
+            Err(_) => false,
+        };
+
+        if !running {
+            if let Ok(mut source_server) = servers.get_mut(source) {
+                if source_server.program == ServerProgram::Hack(target) {
+                    source_server.program = ServerProgram::Wait;
+                }
+            }
+            commands.entity(source).remove::<HackProgress>();
+            ev_interrupted.send(HackInterrupted { source, target });
+            continue;
+        }
+
+        let quarter = hack.elapsed * 4 / hack.required;
+        hack.elapsed += 1;
+        if hack.elapsed < hack.required {
+            if hack.elapsed * 4 / hack.required > quarter {
+                ev_milestone.send(HackMilestone { target });
+            }
+            continue;
+        }
+
+        if let Ok(mut target_server) = servers.get_mut(target) {
+            target_server.state = ServerState::Hacked;
+        }
+        if let Ok(mut source_server) = servers.get_mut(source) {
+            source_server.program = ServerProgram::Wait;
+        }
+        commands.entity(source).remove::<HackProgress>();
+        ev_finished.send(HackFinished { source, target });
+    }
+}
+
+// draw a progress bar above every server that is being hacked.
+fn draw_hack_progress(
+    mut gizmos: Gizmos,
+    hacks: Query<&HackProgress>,
+    transforms: Query<&Transform>,
+) {
+    for hack in &hacks {
+        let Ok(transform) = transforms.get(hack.target) else {
+            continue;
+        };
+        let left = transform.translation.truncate() + Vec2::new(-BAR_WIDTH * 0.5, BAR_OFFSET);
+        gizmos.line_2d(left, left + Vec2::X * BAR_WIDTH, DARK_GRAY);
+        gizmos.line_2d(left, left + Vec2::X * BAR_WIDTH * hack.fraction(), LIME);
+    }
+}
+
+// tell the player how their hacks turned out.
+fn report_hacks(
+    mut ev_finished: EventReader<HackFinished>,
+    mut ev_interrupted: EventReader<HackInterrupted>,
+    mut notices: EventWriter<Notice>,
+    servers: Query<&Server>,
+) {
+    for ev in ev_finished.read() {
+        let (source, target) = (name_of(&servers, ev.source), name_of(&servers, ev.target));
+        notices.send(Notice(format!("{source} finished hacking {target}")));
+    }
+    for ev in ev_interrupted.read() {
+        let (source, target) = (name_of(&servers, ev.source), name_of(&servers, ev.target));
+        notices.send(Notice(format!("{source} stopped hacking {target}")));
+    }
+}
@@ -0,0 +1,151 @@
+use bevy::{
+    color::Mix,
+    prelude::*,
+    render::render_resource::{AsBindGroup, ShaderRef},
+    sprite::{Anchor, Material2d, Material2dPlugin, MaterialMesh2dBundle, Mesh2dHandle},
+};
+use rand::Rng;
+
+use crate::{
+    rendering::PIXEL_PERFECT_LAYERS,
+    server::Server,
+    text_input::Action,
+    TILE_SIZE,
+};
+
+// how far the glow around a server reaches.
+const GLOW_RADIUS: f32 = 3.0 * TILE_SIZE;
+
+// drawn just in front of the server it belongs to, so that the server is tinted too.
+const GLOW_Z: f32 = 0.5;
+
+// how opaque the glow is at the centre of a server that's about to overheat.
+const MAX_ALPHA: f32 = 0.6;
+
+// fraction of `overheat` past which servers start shaking.
+const SHAKE_HEAT: f32 = 0.85;
+
+// how far a server about to overheat is shaken, as a fraction of its sprite.
+const SHAKE_AMOUNT: f32 = 1.0 / 16.0;
+
+const COOL_COLOR: Srgba = Srgba::rgb(0.2, 0.6, 0.9);
+const HOT_COLOR: Srgba = Srgba::rgb(0.95, 0.3, 0.1);
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_plugins(Material2dPlugin::<HeatMaterial>::default())
+        .init_resource::<Heatmap>()
+        .add_systems(
+            Update,
+            (
+                toggle_heatmap,
+                spawn_glows,
+                update_glows,
+                shake_servers,
+            )
+                .chain(),
+        );
+}
+
+// whether the thermal overlay is shown.
+#[derive(Resource, Default)]
+pub struct Heatmap(pub bool);
+
+// tints a soft disc around a server by how close it is to overheating.
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+pub struct HeatMaterial {
+    #[uniform(0)]
+    color: LinearRgba,
+}
+
+impl Material2d for HeatMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/heat.wgsl".into()
+    }
+}
+
+// the glow for the server it's a child of.
+#[derive(Component)]
+struct Glow(Handle<HeatMaterial>);
+
+// run `heatmap` commands.
+fn toggle_heatmap(mut actions: EventReader<Action>, mut heatmap: ResMut<Heatmap>) {
+    for action in actions.read() {
+        if let Action::Heatmap = action {
+            heatmap.0 = !heatmap.0;
+        }
+    }
+}
+
+fn spawn_glows(
+    mut commands: Commands,
+    servers: Query<Entity, Added<Server>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<HeatMaterial>>,
+) {
+    for entity in &servers {
+        let material = materials.add(HeatMaterial {
+            color: LinearRgba::NONE,
+        });
+        let glow = commands
+            .spawn((
+                MaterialMesh2dBundle {
+                    mesh: Mesh2dHandle(meshes.add(Rectangle::from_length(GLOW_RADIUS * 2.0))),
+                    material: material.clone(),
+                    transform: Transform::from_xyz(0.0, 0.0, GLOW_Z),
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+                Glow(material),
+                PIXEL_PERFECT_LAYERS,
+            ))
+            .id();
+        commands.entity(entity).add_child(glow);
+    }
+}
+
+fn update_glows(
+    heatmap: Res<Heatmap>,
+    servers: Query<&Server>,
+    mut glows: Query<(&Parent, &Glow, &mut Visibility)>,
+    mut materials: ResMut<Assets<HeatMaterial>>,
+) {
+    for (parent, glow, mut visibility) in &mut glows {
+        *visibility = if heatmap.0 {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        if !heatmap.0 {
+            continue;
+        }
+
+        let (Ok(server), Some(material)) = (servers.get(parent.get()), materials.get_mut(&glow.0))
+        else {
+            continue;
+        };
+        let heat = (server.temp / server.overheat).clamp(0.0, 1.0);
+        material.color = COOL_COLOR
+            .mix(&HOT_COLOR, heat)
+            .with_alpha(heat * MAX_ALPHA)
+            .into();
+    }
+}
+
+// jitter the sprites of servers that are close to overheating. this moves the sprite's anchor
+// rather than the server itself, so wires and clicks still line up with where it really is.
+fn shake_servers(mut servers: Query<(&Server, &mut Sprite)>) {
+    // the shaking is only for show, so it doesn't need to be the same when replayed.
+    let mut rng = rand::thread_rng();
+    for (server, mut sprite) in &mut servers {
+        let heat = server.temp / server.overheat;
+        if heat < SHAKE_HEAT {
+            if sprite.anchor != Anchor::Center {
+                sprite.anchor = Anchor::Center;
+            }
+            continue;
+        }
+
+        let offset = Vec2::new(rng.gen_range(-1.0..=1.0), rng.gen_range(-1.0..=1.0));
+        sprite.anchor = Anchor::Custom(offset * SHAKE_AMOUNT);
+    }
+}
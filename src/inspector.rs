@@ -0,0 +1,288 @@
+use bevy::{color::Mix, prelude::*};
+
+use crate::{
+    firewall::Firewall,
+    hack::HackProgress,
+    mouse::MouseWorldCoords,
+    server::{find_server, Battery, Cooler, Generator, Server, ServerProgram, Switch},
+    text_input::{Action, Backdrop, Rejected},
+    virus::Virus,
+    wire::Wire,
+    TILE_SIZE,
+};
+
+// how close a click has to be to a server's centre to select it.
+const SELECT_RADIUS: f32 = TILE_SIZE;
+
+const PANEL_WIDTH: f32 = 220.0;
+
+const TEXT_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
+const BACKGROUND_COLOR: Color = Color::srgb(0.15, 0.15, 0.15);
+const BAR_BACKGROUND_COLOR: Color = Color::srgb(0.3, 0.3, 0.3);
+const BAR_COOL_COLOR: Srgba = Srgba::rgb(0.2, 0.6, 0.9);
+const BAR_HOT_COLOR: Srgba = Srgba::rgb(0.95, 0.3, 0.1);
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<Selection>()
+        .add_systems(Startup, setup_inspector)
+        .add_systems(
+            Update,
+            ((select_by_click, select_by_command), update_inspector).chain(),
+        );
+}
+
+// the server currently shown in the inspector.
+#[derive(Resource, Default)]
+pub struct Selection(pub Option<Entity>);
+
+#[derive(Component)]
+struct InspectorPanel;
+
+#[derive(Component)]
+struct InspectorSummary;
+
+#[derive(Component)]
+struct InspectorDetails;
+
+#[derive(Component)]
+struct TempBar;
+
+fn setup_inspector(mut commands: Commands) {
+    let text_style = TextStyle {
+        font_size: 16.,
+        color: TEXT_COLOR,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    display: Display::None,
+                    position_type: PositionType::Absolute,
+                    right: Val::Px(20.0),
+                    top: Val::Px(120.0),
+                    width: Val::Px(PANEL_WIDTH),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(4.0),
+                    padding: UiRect::all(Val::Px(4.0)),
+                    ..default()
+                },
+                background_color: BACKGROUND_COLOR.into(),
+                ..default()
+            },
+            Interaction::default(),
+            InspectorPanel,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section("", text_style.clone()),
+                InspectorSummary,
+            ));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        height: Val::Px(6.0),
+                        ..default()
+                    },
+                    background_color: BAR_BACKGROUND_COLOR.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(0.0),
+                                height: Val::Percent(100.0),
+                                ..default()
+                            },
+                            background_color: Color::from(BAR_COOL_COLOR).into(),
+                            ..default()
+                        },
+                        TempBar,
+                    ));
+                });
+            parent.spawn((TextBundle::from_section("", text_style), InspectorDetails));
+        });
+}
+
+// select whichever server was clicked on, or clear the selection when clicking on nothing.
+// clicks on the text box, minimap or inspector are meant for them, and leave it alone.
+fn select_by_click(
+    mouse: Res<ButtonInput<MouseButton>>,
+    mouse_coords: Res<MouseWorldCoords>,
+    servers: Query<(Entity, &Transform), With<Server>>,
+    ui: Query<&Interaction, Without<Backdrop>>,
+    mut selection: ResMut<Selection>,
+) {
+    if !mouse.just_pressed(MouseButton::Left) {
+        return;
+    }
+    if ui.iter().any(|interaction| *interaction != Interaction::None) {
+        return;
+    }
+
+    selection.0 = servers
+        .iter()
+        .map(|(entity, transform)| {
+            let distance = transform.translation.truncate().distance(mouse_coords.0);
+            (entity, distance)
+        })
+        .filter(|(_, distance)| *distance < SELECT_RADIUS)
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(entity, _)| entity);
+}
+
+// run `inspect <server>` commands.
+fn select_by_command(
+    mut actions: EventReader<Action>,
+    mut rejected: EventWriter<Rejected>,
+    servers: Query<(Entity, &Server)>,
+    mut selection: ResMut<Selection>,
+) {
+    for action in actions.read() {
+        let Action::Inspect { server } = action else {
+            continue;
+        };
+
+        match find_server(&servers, server) {
+            Some(entity) => selection.0 = Some(entity),
+            None => {
+                rejected.send(Rejected(format!("no server named {server}")));
+            }
+        }
+    }
+}
+
+fn update_inspector(
+    selection: Res<Selection>,
+    servers: Query<&Server>,
+    generators: Query<&Generator>,
+    batteries: Query<&Battery>,
+    switches: Query<&Switch>,
+    coolers: Query<(&Cooler, &Transform)>,
+    transforms: Query<&Transform>,
+    instances: Query<&Virus>,
+    wires: Query<(&Wire, Option<&Firewall>)>,
+    hacks: Query<(Entity, &HackProgress)>,
+    mut panel: Query<&mut Style, (With<InspectorPanel>, Without<TempBar>)>,
+    mut summary: Query<&mut Text, (With<InspectorSummary>, Without<InspectorDetails>)>,
+    mut details: Query<&mut Text, (With<InspectorDetails>, Without<InspectorSummary>)>,
+    mut temp_bar: Query<(&mut Style, &mut BackgroundColor), With<TempBar>>,
+) {
+    let selected = selection
+        .0
+        .and_then(|entity| servers.get(entity).ok().map(|server| (entity, server)));
+
+    let Some((entity, server)) = selected else {
+        panel.single_mut().display = Display::None;
+        return;
+    };
+    panel.single_mut().display = Display::Flex;
+
+    let name = |entity: Entity| {
+        servers
+            .get(entity)
+            .map(|server| server.name.clone())
+            .unwrap_or_else(|_| "?".to_string())
+    };
+
+    let program = match server.program {
+        ServerProgram::Heat => "heat".to_string(),
+        ServerProgram::Reboot(remaining) => format!("reboot ({remaining} ticks left)"),
+        ServerProgram::Cycle => "cycle".to_string(),
+        ServerProgram::Hack(target) => format!("hack {}", name(target)),
+        ServerProgram::Wait => "wait".to_string(),
+    };
+    let state = match instances.get(entity) {
+        Ok(virus) => format!("{:?} ({})", server.state, virus.name),
+        Err(_) => format!("{:?}", server.state),
+    };
+    summary.single_mut().sections[0].value = format!(
+        "{}\nstate: {}\nprogram: {}\ntemp: {:.1} / {:.1}",
+        server.name, state, program, server.temp, server.overheat
+    );
+
+    let heat = (server.temp / server.overheat).clamp(0.0, 1.0);
+    let (mut bar_style, mut bar_color) = temp_bar.single_mut();
+    bar_style.width = Val::Percent(heat * 100.0);
+    *bar_color = Color::from(BAR_COOL_COLOR.mix(&BAR_HOT_COLOR, heat)).into();
+
+    let mut lines = vec![];
+
+    let supply = server.supply;
+    lines.push(
+        match (
+            supply.and_then(|supply| generators.get(supply).ok()),
+            supply.and_then(|supply| batteries.get(supply).ok()),
+        ) {
+            (Some(generator), _) => format!(
+                "power: load {:.1} / {:.1}, heat {:.1} / {:.1}",
+                generator.load, generator.overload, generator.heat, generator.overheat
+            ),
+            (_, Some(battery)) => format!(
+                "power: battery {:.0}%{}",
+                battery.charge / battery.capacity * 100.0,
+                if battery.grid.is_some() { "" } else { ", draining" }
+            ),
+            _ => "power: none".to_string(),
+        },
+    );
+
+    let neighbours: Vec<_> = wires
+        .iter()
+        .filter_map(|(wire, firewall)| {
+            let other = match wire.terminals {
+                [a, b] if a == entity => b,
+                [a, b] if b == entity => a,
+                _ => return None,
+            };
+            let name = match (servers.get(other), switches.get(other)) {
+                (Ok(server), _) => server.name.clone(),
+                (_, Ok(switch)) if switch.on() => switch.name.clone(),
+                (_, Ok(switch)) => format!("{} (off)", switch.name),
+                _ => return None,
+            };
+            let name = match firewall {
+                Some(firewall) if firewall.up => format!("{name} (firewall)"),
+                _ => name,
+            };
+            Some(if wire.cuts.is_empty() {
+                name
+            } else {
+                format!("{name} (cut)")
+            })
+        })
+        .collect();
+    lines.push(format!("wired to: {}", neighbours.join(", ")));
+
+    let position = transforms.get(entity).map_or(Vec2::ZERO, |t| t.translation.truncate());
+    let cooled_by: Vec<_> = coolers
+        .iter()
+        .filter(|(cooler, transform)| {
+            transform.translation.truncate().distance(position) <= cooler.radius * TILE_SIZE
+        })
+        .map(|(cooler, _)| {
+            if cooler.on() {
+                cooler.name.clone()
+            } else {
+                format!("{} (off)", cooler.name)
+            }
+        })
+        .collect();
+    if !cooled_by.is_empty() {
+        lines.push(format!("cooled by: {}", cooled_by.join(", ")));
+    }
+
+    for (source, hack) in &hacks {
+        let progress = hack.fraction() * 100.0;
+        if source == entity {
+            lines.push(format!("hacking {}: {:.0}%", name(hack.target), progress));
+        } else if hack.target == entity {
+            lines.push(format!("being hacked by {}: {:.0}%", name(source), progress));
+        }
+    }
+
+    details.single_mut().sections[0].value = lines.join("\n");
+}
@@ -0,0 +1,341 @@
+use bevy::{prelude::*, utils::HashSet};
+
+use crate::{
+    clock::{run_ticks, Clock},
+    defender::Defender,
+    economy::Compute,
+    hack::HackProgress,
+    level::{LevelConfig, LevelMode, LevelRng},
+    loading::GameState,
+    server::{Battery, Generator, Server, ServerProgram, ServerState},
+    replay::play_back,
+    text_input::{command, Action, Rejected},
+    upgrades::Upgrades,
+    virus::{LevelOutcome, Virus},
+    wire::Wire,
+};
+
+// most commands that can be undone at once. the oldest are forgotten first.
+const MAX_UNDO: usize = 100;
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<Journal>()
+        .add_systems(OnEnter(GameState::Playing), clear_journal)
+        .add_systems(
+            PreUpdate,
+            take_snapshot
+                .after(run_ticks)
+                .after(play_back)
+                .before(command)
+                .run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(Update, undo_commands.run_if(in_state(GameState::Playing)))
+        .add_systems(PostUpdate, record_commands.run_if(in_state(GameState::Playing)));
+}
+
+// the state of the network before each command, so that puzzle levels can be rewound with
+// `undo` and played forward again with `redo`.
+#[derive(Resource, Default)]
+pub struct Journal {
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+    // the network as it was this frame, after its ticks but before any commands were carried
+    // out.
+    latest: Option<Snapshot>,
+}
+
+// everything a command, or the ticks it runs, can change.
+#[derive(Clone, PartialEq)]
+struct Snapshot {
+    servers: Vec<(Entity, ServerSnapshot)>,
+    wires: Vec<(Entity, HashSet<usize>)>,
+    // heat and whether it's online.
+    generators: Vec<(Entity, f32, bool)>,
+    batteries: Vec<(Entity, f32)>,
+    compute: f32,
+    upgrades: Upgrades,
+    defender: Defender,
+    rng: LevelRng,
+    outcome: LevelOutcome,
+    pending: usize,
+    elapsed: usize,
+}
+
+#[derive(Clone, PartialEq)]
+struct ServerSnapshot {
+    state: ServerState,
+    program: ServerProgram,
+    temp: f32,
+    // the instance of the virus running on the server, if any.
+    virus: Option<String>,
+    // the target of the hack the server is running, with its elapsed and required ticks.
+    hack: Option<(Entity, usize, usize)>,
+}
+
+// whether a command moves the game on or spends compute, and so gets a place in the journal.
+fn journaled(action: &Action) -> bool {
+    matches!(
+        action,
+        Action::Wait
+            | Action::Move { .. }
+            | Action::Fork { .. }
+            | Action::Hack { .. }
+            | Action::Reboot { .. }
+            | Action::Cycle { .. }
+            | Action::Heat { .. }
+            | Action::Cut { .. }
+            | Action::Upgrade { .. }
+    )
+}
+
+fn clear_journal(mut journal: ResMut<Journal>) {
+    *journal = Journal::default();
+}
+
+fn snapshot(
+    servers: &Query<(Entity, &Server, Option<&Virus>, Option<&HackProgress>)>,
+    wires: &Query<(Entity, &Wire)>,
+    generators: &Query<(Entity, &Generator)>,
+    batteries: &Query<(Entity, &Battery)>,
+    compute: &Compute,
+    upgrades: &Upgrades,
+    defender: &Defender,
+    rng: &LevelRng,
+    outcome: &LevelOutcome,
+    clock: &Clock,
+) -> Snapshot {
+    Snapshot {
+        servers: servers
+            .iter()
+            .map(|(entity, server, virus, hack)| {
+                let server = ServerSnapshot {
+                    state: server.state,
+                    program: server.program,
+                    temp: server.temp,
+                    virus: virus.map(|virus| virus.name.clone()),
+                    hack: hack.map(|hack| (hack.target, hack.elapsed, hack.required)),
+                };
+                (entity, server)
+            })
+            .collect(),
+        wires: wires
+            .iter()
+            .map(|(entity, wire)| (entity, wire.cuts.clone()))
+            .collect(),
+        generators: generators
+            .iter()
+            .map(|(entity, generator)| (entity, generator.heat, generator.online))
+            .collect(),
+        batteries: batteries
+            .iter()
+            .map(|(entity, battery)| (entity, battery.charge))
+            .collect(),
+        compute: compute.0,
+        upgrades: upgrades.clone(),
+        defender: defender.clone(),
+        rng: rng.clone(),
+        outcome: outcome.clone(),
+        pending: clock.pending,
+        elapsed: clock.elapsed,
+    }
+}
+
+fn take_snapshot(
+    mut journal: ResMut<Journal>,
+    servers: Query<(Entity, &Server, Option<&Virus>, Option<&HackProgress>)>,
+    wires: Query<(Entity, &Wire)>,
+    generators: Query<(Entity, &Generator)>,
+    batteries: Query<(Entity, &Battery)>,
+    compute: Res<Compute>,
+    upgrades: Res<Upgrades>,
+    defender: Res<Defender>,
+    rng: Res<LevelRng>,
+    outcome: Res<LevelOutcome>,
+    clock: Res<Clock>,
+) {
+    journal.latest = Some(snapshot(
+        &servers,
+        &wires,
+        &generators,
+        &batteries,
+        &compute,
+        &upgrades,
+        &defender,
+        &rng,
+        &outcome,
+        &clock,
+    ));
+}
+
+// put the network back the way it was in `snapshot`.
+fn restore(
+    commands: &mut Commands,
+    snapshot: &Snapshot,
+    servers: &mut Query<(Entity, &mut Server, Option<&Virus>, Option<&HackProgress>)>,
+    wires: &mut Query<(Entity, &mut Wire)>,
+    generators: &mut Query<(Entity, &mut Generator)>,
+    batteries: &mut Query<(Entity, &mut Battery)>,
+    compute: &mut Compute,
+    upgrades: &mut Upgrades,
+    defender: &mut Defender,
+    rng: &mut LevelRng,
+    outcome: &mut LevelOutcome,
+    clock: &mut Clock,
+) {
+    for (entity, saved) in &snapshot.servers {
+        let Ok((_, mut server, _, _)) = servers.get_mut(*entity) else {
+            continue;
+        };
+        server.state = saved.state;
+        server.program = saved.program;
+        server.temp = saved.temp;
+
+        let mut entity = commands.entity(*entity);
+        match &saved.virus {
+            Some(name) => entity.insert(Virus { name: name.clone() }),
+            None => entity.remove::<Virus>(),
+        };
+        match saved.hack {
+            Some((target, elapsed, required)) => entity.insert(HackProgress {
+                target,
+                elapsed,
+                required,
+            }),
+            None => entity.remove::<HackProgress>(),
+        };
+    }
+
+    for (entity, cuts) in &snapshot.wires {
+        if let Ok((_, mut wire)) = wires.get_mut(*entity) {
+            // only touch wires that changed, so that the rest keep their meshes.
+            if wire.cuts != *cuts {
+                wire.cuts = cuts.clone();
+            }
+        }
+    }
+
+    for &(entity, heat, online) in &snapshot.generators {
+        if let Ok((_, mut generator)) = generators.get_mut(entity) {
+            generator.heat = heat;
+            generator.online = online;
+        }
+    }
+
+    for &(entity, charge) in &snapshot.batteries {
+        if let Ok((_, mut battery)) = batteries.get_mut(entity) {
+            battery.charge = charge;
+        }
+    }
+
+    compute.0 = snapshot.compute;
+    *upgrades = snapshot.upgrades.clone();
+    *defender = snapshot.defender.clone();
+    *rng = snapshot.rng.clone();
+    *outcome = snapshot.outcome.clone();
+    clock.pending = snapshot.pending;
+    clock.elapsed = snapshot.elapsed;
+}
+
+// note down the network as it was before every command that moves the game on, once
+// they've all been carried out. commands that were rejected leave the network as it was, so
+// they don't get an entry. commands come in one at a time, so any rejection is for the
+// command that came with it.
+fn record_commands(
+    mut actions: EventReader<Action>,
+    mut rejected: EventReader<Rejected>,
+    mut journal: ResMut<Journal>,
+) {
+    let acted = actions.read().filter(|action| journaled(action)).count();
+    let rejected = rejected.read().count();
+    if acted.saturating_sub(rejected) == 0 {
+        return;
+    }
+    let Some(latest) = journal.latest.clone() else {
+        return;
+    };
+
+    journal.undo.push(latest);
+    if journal.undo.len() > MAX_UNDO {
+        journal.undo.remove(0);
+    }
+    journal.redo.clear();
+}
+
+// run `undo` and `redo` commands.
+fn undo_commands(
+    mut commands: Commands,
+    mut actions: EventReader<Action>,
+    mut rejected: EventWriter<Rejected>,
+    mut journal: ResMut<Journal>,
+    config: Res<LevelConfig>,
+    mut servers: Query<(Entity, &mut Server, Option<&Virus>, Option<&HackProgress>)>,
+    mut wires: Query<(Entity, &mut Wire)>,
+    mut generators: Query<(Entity, &mut Generator)>,
+    mut batteries: Query<(Entity, &mut Battery)>,
+    mut compute: ResMut<Compute>,
+    mut upgrades: ResMut<Upgrades>,
+    mut defender: ResMut<Defender>,
+    mut rng: ResMut<LevelRng>,
+    mut outcome: ResMut<LevelOutcome>,
+    mut clock: ResMut<Clock>,
+) {
+    for action in actions.read() {
+        let undo = match action {
+            Action::Undo => true,
+            Action::Redo => false,
+            _ => continue,
+        };
+
+        if config.mode == LevelMode::Challenge {
+            rejected.send(Rejected("there's no going back in challenge mode".to_string()));
+            continue;
+        }
+
+        let current = snapshot(
+            &servers.to_readonly(),
+            &wires.to_readonly(),
+            &generators.to_readonly(),
+            &batteries.to_readonly(),
+            &compute,
+            &upgrades,
+            &defender,
+            &rng,
+            &outcome,
+            &clock,
+        );
+        let journal = &mut *journal;
+        let (from, to) = if undo {
+            (&mut journal.undo, &mut journal.redo)
+        } else {
+            (&mut journal.redo, &mut journal.undo)
+        };
+
+        // skip over entries for commands that didn't end up changing anything.
+        while from.last() == Some(&current) {
+            from.pop();
+        }
+        let Some(saved) = from.pop() else {
+            let verb = if undo { "undo" } else { "redo" };
+            rejected.send(Rejected(format!("nothing to {verb}")));
+            continue;
+        };
+
+        restore(
+            &mut commands,
+            &saved,
+            &mut servers,
+            &mut wires,
+            &mut generators,
+            &mut batteries,
+            &mut compute,
+            &mut upgrades,
+            &mut defender,
+            &mut rng,
+            &mut outcome,
+            &mut clock,
+        );
+        to.push(current);
+        // the snapshot for this frame is stale now.
+        journal.latest = Some(saved);
+    }
+}
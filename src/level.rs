@@ -0,0 +1,858 @@
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    args::Args,
+    assetloader::{AtlasKey, HandleMap, ImageKey},
+    firewall::Firewall,
+    loading::GameState,
+    routing::{route, Obstacle, Route, Routing},
+    server::{
+        Battery, Cooler, Generator, Server, ServerProgram, ServerSprite, ServerState, Switch,
+    },
+    solver::{Puzzle, MAX_STEPS},
+    topology::{build, Topology},
+    virus::Virus,
+    wire::Wire,
+    TILE_SIZE,
+};
+
+// nodes are placed on tiles within this many tiles of the origin.
+const PLACEMENT_RADIUS: i32 = 10;
+
+// nodes can't be placed any closer than this many tiles to each other.
+const MIN_SPACING: i32 = 3;
+
+// the background is tiled this many tiles out from the origin.
+const FLOOR_RADIUS: i32 = 50;
+
+// how many times the force-directed layout moves every node.
+const LAYOUT_ITERATIONS: usize = 200;
+
+// how much the furthest a node can move in one iteration shrinks by each iteration.
+const LAYOUT_COOLING: f32 = 0.97;
+
+// how many tiles across servers and generators are.
+const SERVER_SIZE: f32 = 2.0;
+const GENERATOR_SIZE: f32 = 1.0;
+const SWITCH_SIZE: f32 = 1.0;
+const BATTERY_SIZE: f32 = 1.0;
+const COOLER_SIZE: f32 = 1.0;
+
+// how many tiles away coolers reach.
+const COOLER_RADIUS: f32 = 10.0;
+
+// most servers a single battery backs up.
+const BATTERY_SERVERS: usize = 3;
+
+// how many seeds after `LevelConfig::seed` are tried before firewalls and hacked servers are
+// left out to make a level that can be won.
+const MAX_ATTEMPTS: u64 = 20;
+
+// how many straight pieces wires are drawn with.
+const WIRE_SEGMENTS: usize = 32;
+
+// half the thickness of a wire.
+const WIRE_WIDTH: f32 = 0.5;
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<LevelConfig>()
+        .init_resource::<LevelRng>()
+        .add_systems(OnEnter(GameState::Playing), setup_level);
+}
+
+// how levels get generated.
+#[derive(Resource, Clone)]
+pub struct LevelConfig {
+    // everything random about a level, from how it's generated to what the defender does,
+    // comes from this. the same seed and commands always play out the same way.
+    pub seed: u64,
+    pub mode: LevelMode,
+    pub topology: Topology,
+    pub generators: usize,
+    pub placement: Placement,
+    pub routing: Routing,
+    // chance of each server, other than the one the virus starts on, being hacked already.
+    pub hacked_chance: f64,
+    // chance of each communication link getting a firewall.
+    pub firewall_chance: f64,
+    // how many batteries back up servers when their generator goes.
+    pub batteries: usize,
+    // chance of each server getting wired to a second generator.
+    pub redundancy: f64,
+    // how many crac units keep the servers around them from overheating.
+    pub coolers: usize,
+}
+
+impl Default for LevelConfig {
+    fn default() -> Self {
+        Self {
+            seed: rand::random(),
+            mode: LevelMode::Puzzle,
+            topology: Topology::default(),
+            generators: 2,
+            placement: Placement::Random,
+            routing: Routing::Random,
+            hacked_chance: 0.7,
+            firewall_chance: 0.15,
+            batteries: 1,
+            redundancy: 0.2,
+            coolers: 2,
+        }
+    }
+}
+
+impl LevelConfig {
+    // the default level, with anything given on the command line swapped in:
+    //
+    //     --mode <puzzle|challenge>
+    //     --placement <random|force>
+    //     --routing <random|auto>
+    //     --topology <random|ring|star|tree|mesh|datacenter>
+    pub fn from_args(args: &mut Args) -> Self {
+        let mut config = LevelConfig::default();
+        if let Some(mode) = args.named("mode", LevelMode::from_name) {
+            config.mode = mode;
+        }
+        if let Some(placement) = args.named("placement", Placement::from_name) {
+            config.placement = placement;
+        }
+        if let Some(routing) = args.named("routing", Routing::from_name) {
+            config.routing = routing;
+        }
+        if let Some(topology) = args.named("topology", Topology::from_name) {
+            config.topology = topology;
+        }
+        config
+    }
+}
+
+// randomness for the level being played, seeded from `LevelConfig::seed`. anything random
+// that affects the simulation has to come from here, or replays won't play out the same.
+#[derive(Resource, Clone, PartialEq)]
+pub struct LevelRng(pub StdRng);
+
+impl Default for LevelRng {
+    fn default() -> Self {
+        Self(StdRng::seed_from_u64(0))
+    }
+}
+
+// the rules a level is played by.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum LevelMode {
+    // commands can be taken back with `undo` and `redo`, and the clock starts out turn-based.
+    Puzzle,
+    // every command sticks, and the clock starts out running in real time.
+    Challenge,
+}
+
+impl LevelMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "puzzle" => Some(LevelMode::Puzzle),
+            "challenge" => Some(LevelMode::Challenge),
+            _ => None,
+        }
+    }
+}
+
+// how nodes are laid out on the tile grid.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Placement {
+    // anywhere within `PLACEMENT_RADIUS` of the origin.
+    Random,
+    // linked nodes pull together and everything else pushes apart,
+    // so that related servers cluster and wires rarely cross.
+    ForceDirected,
+}
+
+impl Placement {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "random" => Some(Placement::Random),
+            "force" => Some(Placement::ForceDirected),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum NodeKind {
+    Server,
+    Generator,
+    Switch,
+    Battery,
+    Cooler,
+}
+
+// the nodes of a network and how they're wired together, before it's laid out and spawned.
+#[derive(Default, Debug)]
+pub struct Blueprint {
+    pub nodes: Vec<NodeKind>,
+    // indices into `nodes`.
+    pub links: Vec<(usize, usize)>,
+    // the server the virus starts out on. the first server, if this isn't set.
+    pub start: Option<usize>,
+    // links that have a firewall, and the node hosting each one.
+    pub firewalls: HashMap<usize, usize>,
+    // servers that start out already hacked.
+    pub hacked: HashSet<usize>,
+}
+
+impl Blueprint {
+    pub fn add(&mut self, kind: NodeKind) -> usize {
+        self.nodes.push(kind);
+        self.nodes.len() - 1
+    }
+
+    pub fn link(&mut self, a: usize, b: usize) {
+        self.links.push((a, b));
+    }
+
+    // put a firewall on some of the links between servers and switches, hosted at one end.
+    pub fn add_firewalls(&mut self, chance: f64, rng: &mut impl Rng) {
+        for (i, &(a, b)) in self.links.iter().enumerate() {
+            let powered = [a, b]
+                .iter()
+                .any(|&n| matches!(self.nodes[n], NodeKind::Generator | NodeKind::Battery));
+            if !powered && rng.gen_bool(chance) {
+                self.firewalls.insert(i, if rng.gen_bool(0.5) { a } else { b });
+            }
+        }
+    }
+
+    // the server the virus starts out on.
+    pub fn start_server(&self) -> Option<usize> {
+        self.start
+            .or_else(|| self.nodes.iter().position(|kind| *kind == NodeKind::Server))
+    }
+
+    // mark some of the servers as hacked already. the virus' server is infected instead.
+    pub fn add_hacked(&mut self, chance: f64, rng: &mut impl Rng) {
+        let start = self.start_server();
+        let servers: Vec<_> = (0..self.nodes.len())
+            .filter(|&n| self.nodes[n] == NodeKind::Server && Some(n) != start)
+            .collect();
+        for server in servers {
+            if rng.gen_bool(chance) {
+                self.hacked.insert(server);
+            }
+        }
+    }
+
+    // what every node is called once it's spawned. generators and batteries go unnamed.
+    pub fn names(&self) -> Vec<String> {
+        let mut servers = 0;
+        let mut switches = 0;
+        let mut coolers = 0;
+        self.nodes
+            .iter()
+            .map(|kind| match kind {
+                NodeKind::Server => {
+                    servers += 1;
+                    server_name(servers - 1)
+                }
+                NodeKind::Switch => {
+                    switches += 1;
+                    format!("sw{switches}")
+                }
+                NodeKind::Cooler => {
+                    coolers += 1;
+                    format!("crac{coolers}")
+                }
+                NodeKind::Generator | NodeKind::Battery => String::new(),
+            })
+            .collect()
+    }
+
+    // the coolers close enough to `node` to keep it cool, once the nodes are placed on `tiles`.
+    pub fn coolers_near(&self, tiles: &[IVec2], node: usize) -> Vec<usize> {
+        let centre = |n: usize| {
+            get_transform(tiles[n], node_size(self.nodes[n]), 0.0)
+                .translation
+                .truncate()
+        };
+        (0..self.nodes.len())
+            .filter(|&n| self.nodes[n] == NodeKind::Cooler)
+            .filter(|&n| centre(n).distance(centre(node)) <= COOLER_RADIUS * TILE_SIZE)
+            .collect()
+    }
+
+    // the generators wired straight to `node`.
+    fn generators(&self, node: usize) -> Vec<usize> {
+        self.links
+            .iter()
+            .filter_map(|&(a, b)| match (a == node, b == node) {
+                (true, _) => Some(b),
+                (_, true) => Some(a),
+                _ => None,
+            })
+            .filter(|&n| self.nodes[n] == NodeKind::Generator)
+            .collect()
+    }
+
+    // add `coolers` coolers, each powered by one of the generators.
+    pub fn add_coolers(&mut self, coolers: usize, rng: &mut impl Rng) {
+        let generators: Vec<_> = (0..self.nodes.len())
+            .filter(|&n| self.nodes[n] == NodeKind::Generator)
+            .collect();
+        if generators.is_empty() {
+            return;
+        }
+
+        for _ in 0..coolers {
+            let cooler = self.add(NodeKind::Cooler);
+            self.link(cooler, generators[rng.gen_range(0..generators.len())]);
+        }
+    }
+
+    // wire some servers to a second generator to fail over to, and add `batteries` batteries,
+    // each charging from a generator and backing up a few of the servers it powers.
+    pub fn add_backup_power(&mut self, batteries: usize, redundancy: f64, rng: &mut impl Rng) {
+        let generators: Vec<_> = (0..self.nodes.len())
+            .filter(|&n| self.nodes[n] == NodeKind::Generator)
+            .collect();
+        if generators.is_empty() {
+            return;
+        }
+
+        let servers: Vec<_> = (0..self.nodes.len())
+            .filter(|&n| self.nodes[n] == NodeKind::Server)
+            .collect();
+        for &server in &servers {
+            let spare: Vec<_> = generators
+                .iter()
+                .copied()
+                .filter(|g| !self.generators(server).contains(g))
+                .collect();
+            if !spare.is_empty() && rng.gen_bool(redundancy) {
+                self.link(server, spare[rng.gen_range(0..spare.len())]);
+            }
+        }
+
+        for _ in 0..batteries {
+            let generator = generators[rng.gen_range(0..generators.len())];
+            let battery = self.add(NodeKind::Battery);
+            self.link(battery, generator);
+            let backed = servers
+                .iter()
+                .copied()
+                .filter(|&server| self.generators(server).contains(&generator))
+                .take(BATTERY_SERVERS)
+                .collect::<Vec<_>>();
+            for server in backed {
+                self.link(battery, server);
+            }
+        }
+    }
+}
+
+// pick a tile for every node in the blueprint.
+pub fn place(blueprint: &Blueprint, placement: Placement, rng: &mut impl Rng) -> Vec<IVec2> {
+    let positions = match placement {
+        Placement::Random => blueprint
+            .nodes
+            .iter()
+            .map(|_| random_position(rng))
+            .collect(),
+        Placement::ForceDirected => force_directed(blueprint, rng),
+    };
+    snap_to_grid(&positions)
+}
+
+fn random_position(rng: &mut impl Rng) -> Vec2 {
+    let radius = PLACEMENT_RADIUS as f32;
+    Vec2::new(rng.gen_range(-radius..radius), rng.gen_range(-radius..radius))
+}
+
+// lay the nodes out in tile space with a fruchterman-reingold simulation: every pair of nodes
+// pushes apart, and linked nodes pull together.
+fn force_directed(blueprint: &Blueprint, rng: &mut impl Rng) -> Vec<Vec2> {
+    let count = blueprint.nodes.len();
+    let radius = PLACEMENT_RADIUS as f32;
+    // the distance nodes settle at, if they're spread evenly over the placement area.
+    let ideal = ((2.0 * radius).powi(2) / count.max(1) as f32).sqrt();
+
+    let mut positions: Vec<_> = (0..count).map(|_| random_position(rng)).collect();
+    let mut max_step = radius * 0.5;
+    for _ in 0..LAYOUT_ITERATIONS {
+        let mut forces = vec![Vec2::ZERO; count];
+        for a in 0..count {
+            for b in a + 1..count {
+                let delta = positions[a] - positions[b];
+                let distance = delta.length().max(0.01);
+                let push = delta / distance * ideal * ideal / distance;
+                forces[a] += push;
+                forces[b] -= push;
+            }
+        }
+        for &(a, b) in &blueprint.links {
+            let delta = positions[a] - positions[b];
+            let distance = delta.length().max(0.01);
+            let pull = delta / distance * distance * distance / ideal;
+            forces[a] -= pull;
+            forces[b] += pull;
+        }
+
+        for (position, force) in positions.iter_mut().zip(forces) {
+            *position += force.clamp_length_max(max_step);
+            *position = position.clamp(Vec2::splat(-radius), Vec2::splat(radius));
+        }
+        max_step *= LAYOUT_COOLING;
+    }
+    positions
+}
+
+// move every node to the nearest tile that's at least `MIN_SPACING` from the nodes before it.
+fn snap_to_grid(positions: &[Vec2]) -> Vec<IVec2> {
+    let mut taken: Vec<IVec2> = vec![];
+    for &position in positions {
+        let centre = position.round().as_ivec2();
+        let free = |tile: &IVec2| {
+            taken
+                .iter()
+                .all(|other| (*other - *tile).abs().max_element() >= MIN_SPACING)
+        };
+
+        // search outwards a ring at a time, taking the closest free tile in the first ring
+        // with one.
+        let tile = (0..)
+            .find_map(|ring: i32| {
+                (-ring..=ring)
+                    .flat_map(|x| (-ring..=ring).map(move |y| IVec2::new(x, y)))
+                    .filter(|offset| offset.abs().max_element() == ring)
+                    .map(|offset| centre + offset)
+                    .filter(|tile| free(tile))
+                    .min_by(|a, b| {
+                        let a = a.as_vec2().distance_squared(position);
+                        let b = b.as_vec2().distance_squared(position);
+                        a.total_cmp(&b)
+                    })
+            })
+            .expect("there's always a free tile further out");
+        taken.push(tile);
+    }
+    taken
+}
+
+fn node_size(kind: NodeKind) -> f32 {
+    match kind {
+        NodeKind::Server => SERVER_SIZE,
+        NodeKind::Generator => GENERATOR_SIZE,
+        NodeKind::Switch => SWITCH_SIZE,
+        NodeKind::Battery => BATTERY_SIZE,
+        NodeKind::Cooler => COOLER_SIZE,
+    }
+}
+
+// shape the wires for every link in the blueprint, once its nodes have been placed.
+pub fn route_links(
+    blueprint: &Blueprint,
+    tiles: &[IVec2],
+    routing: Routing,
+    rng: &mut impl Rng,
+) -> Vec<Route> {
+    let obstacles: Vec<_> = blueprint
+        .nodes
+        .iter()
+        .zip(tiles)
+        .map(|(&kind, &tile)| Obstacle {
+            tile,
+            centre: get_transform(tile, node_size(kind), 0.0)
+                .translation
+                .truncate(),
+            radius: node_size(kind) * TILE_SIZE * 0.5,
+        })
+        .collect();
+    route(&obstacles, &blueprint.links, routing, rng)
+}
+
+pub fn get_transform(loc: IVec2, size: f32, z: f32) -> Transform {
+    Transform::from_xyz(
+        loc.x as f32 * TILE_SIZE - TILE_SIZE * size * 0.5,
+        loc.y as f32 * TILE_SIZE - TILE_SIZE * size * 0.5,
+        z,
+    )
+}
+
+pub fn spawn_server(
+    commands: &mut Commands,
+    images: &HandleMap<ImageKey>,
+    atlases: &HandleMap<AtlasKey>,
+    tile: IVec2,
+    name: String,
+    state: ServerState,
+    defence: f32,
+) -> Entity {
+    commands
+        .spawn((
+            SpriteBundle {
+                texture: images.get_or_missing(&ImageKey::Missing),
+                transform: get_transform(tile, SERVER_SIZE, 0.0),
+                ..default()
+            },
+            TextureAtlas::from(atlases[&AtlasKey::Single].clone()),
+            ServerSprite::default(),
+            Server {
+                state,
+                program: ServerProgram::Wait,
+                temp: 0.0,
+                overheat: 10.0,
+                defence,
+                power: 1.0,
+                supply: None,
+                name: name.clone(),
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(Text2dBundle {
+                text: Text::from_section(name, TextStyle::default()),
+                transform: Transform::from_xyz(-12.0, -12.0, 0.0)
+                    .with_scale(Vec3::new(0.5, 0.5, 1.0)),
+                ..default()
+            });
+        })
+        .id()
+}
+
+pub fn spawn_generator(
+    commands: &mut Commands,
+    images: &HandleMap<ImageKey>,
+    tile: IVec2,
+) -> Entity {
+    commands
+        .spawn((
+            SpriteBundle {
+                texture: images.get_or_missing(&ImageKey::Power),
+                transform: get_transform(tile, GENERATOR_SIZE, 0.0),
+                ..default()
+            },
+            Generator {
+                load: 0.0,
+                overload: 4.0,
+                heat: 0.0,
+                overheat: 5.0,
+                online: true,
+            },
+        ))
+        .id()
+}
+
+pub fn spawn_battery(commands: &mut Commands, images: &HandleMap<ImageKey>, tile: IVec2) -> Entity {
+    commands
+        .spawn((
+            SpriteBundle {
+                texture: images.get_or_missing(&ImageKey::Battery),
+                transform: get_transform(tile, BATTERY_SIZE, 0.0),
+                ..default()
+            },
+            Battery {
+                charge: 40.0,
+                capacity: 40.0,
+                grid: None,
+            },
+        ))
+        .id()
+}
+
+pub fn spawn_cooler(
+    commands: &mut Commands,
+    images: &HandleMap<ImageKey>,
+    tile: IVec2,
+    name: String,
+) -> Entity {
+    commands
+        .spawn((
+            SpriteBundle {
+                texture: images.get_or_missing(&ImageKey::Cooler),
+                transform: get_transform(tile, COOLER_SIZE, 0.0),
+                ..default()
+            },
+            Cooler {
+                name: name.clone(),
+                power: 1.0,
+                cooling: 0.02,
+                radius: COOLER_RADIUS,
+                supply: None,
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(Text2dBundle {
+                text: Text::from_section(name, TextStyle::default()),
+                transform: Transform::from_xyz(-8.0, -10.0, 0.0)
+                    .with_scale(Vec3::new(0.5, 0.5, 1.0)),
+                ..default()
+            });
+        })
+        .id()
+}
+
+pub fn spawn_switch(
+    commands: &mut Commands,
+    images: &HandleMap<ImageKey>,
+    tile: IVec2,
+    name: String,
+) -> Entity {
+    commands
+        .spawn((
+            SpriteBundle {
+                texture: images.get_or_missing(&ImageKey::Switch),
+                transform: get_transform(tile, SWITCH_SIZE, 0.0),
+                ..default()
+            },
+            Switch {
+                name: name.clone(),
+                power: 0.5,
+                supply: None,
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(Text2dBundle {
+                text: Text::from_section(name, TextStyle::default()),
+                transform: Transform::from_xyz(-8.0, -10.0, 0.0)
+                    .with_scale(Vec3::new(0.5, 0.5, 1.0)),
+                ..default()
+            });
+        })
+        .id()
+}
+
+pub fn spawn_wire(commands: &mut Commands, a: Entity, b: Entity, route: Route) -> Entity {
+    commands
+        .spawn(Wire {
+            terminals: [a, b],
+            control_points: route.control_points,
+            corners: route.corners,
+            segments: WIRE_SEGMENTS,
+            cuts: HashSet::default(),
+            width: WIRE_WIDTH,
+        })
+        .id()
+}
+
+// servers are named a to z, then aa, ab and so on.
+fn server_name(mut index: usize) -> String {
+    let mut name = vec![];
+    loop {
+        name.push(b'a' + (index % 26) as u8);
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    name.reverse();
+    String::from_utf8(name).expect("names are ascii")
+}
+
+// spawn every node in the blueprint on its tile, and wire them up. the starting server is
+// infected, and the rest are either healthy or already hacked.
+pub fn spawn_blueprint(
+    commands: &mut Commands,
+    images: &HandleMap<ImageKey>,
+    atlases: &HandleMap<AtlasKey>,
+    blueprint: &Blueprint,
+    tiles: &[IVec2],
+    routes: Vec<Route>,
+    rng: &mut impl Rng,
+) {
+    let start = blueprint.start_server();
+    let entities: Vec<_> = blueprint
+        .nodes
+        .iter()
+        .zip(tiles)
+        .zip(blueprint.names())
+        .enumerate()
+        .map(|(i, ((kind, &tile), name))| match kind {
+            NodeKind::Server => {
+                let state = if Some(i) == start {
+                    ServerState::Infected
+                } else if blueprint.hacked.contains(&i) {
+                    ServerState::Hacked
+                } else {
+                    ServerState::Healthy
+                };
+                let defence = rng.gen_range(1.0..3.0);
+                let server = spawn_server(commands, images, atlases, tile, name, state, defence);
+                if state == ServerState::Infected {
+                    commands.entity(server).insert(Virus {
+                        name: "v1".to_string(),
+                    });
+                }
+                server
+            }
+            NodeKind::Generator => spawn_generator(commands, images, tile),
+            NodeKind::Battery => spawn_battery(commands, images, tile),
+            NodeKind::Cooler => spawn_cooler(commands, images, tile, name),
+            NodeKind::Switch => spawn_switch(commands, images, tile, name),
+        })
+        .collect();
+
+    for (i, (&(a, b), route)) in blueprint.links.iter().zip(routes).enumerate() {
+        let wire = spawn_wire(commands, entities[a], entities[b], route);
+        if let Some(&host) = blueprint.firewalls.get(&i) {
+            commands.entity(wire).insert(Firewall {
+                host: entities[host],
+                up: true,
+            });
+        }
+    }
+}
+
+// generate a blueprint from `seed` and place it, handing back the rng to carry on with.
+fn generate(config: &LevelConfig, seed: u64) -> (Blueprint, Vec<IVec2>, StdRng) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut blueprint = build(&config.topology, config.generators, &mut rng);
+    blueprint.add_hacked(config.hacked_chance, &mut rng);
+    blueprint.add_coolers(config.coolers, &mut rng);
+    blueprint.add_backup_power(config.batteries, config.redundancy, &mut rng);
+    blueprint.add_firewalls(config.firewall_chance, &mut rng);
+    let tiles = place(&blueprint, config.placement, &mut rng);
+    (blueprint, tiles, rng)
+}
+
+// levels that can't be won, or that are won before anything's been done, are thrown away,
+// and the next seed along is tried instead. this only depends on the seed, so replays still
+// get the same level.
+fn generate_solvable(config: &LevelConfig) -> (Blueprint, Vec<IVec2>, StdRng) {
+    let solvable = |(blueprint, tiles, _): &(Blueprint, Vec<IVec2>, StdRng)| {
+        Puzzle::from_blueprint(blueprint, tiles)
+            .solve(MAX_STEPS)
+            .is_some_and(|steps| !steps.is_empty())
+    };
+    (0..MAX_ATTEMPTS)
+        .map(|attempt| generate(config, config.seed.wrapping_add(attempt)))
+        .find(solvable)
+        .or_else(|| {
+            // without firewalls, a virus with nothing hacked yet can hack its way across any
+            // connected network.
+            warn!("no level from this seed can be won, leaving out firewalls and hacked servers");
+            let relaxed = LevelConfig {
+                hacked_chance: 0.0,
+                firewall_chance: 0.0,
+                ..config.clone()
+            };
+            Some(generate(&relaxed, config.seed)).filter(solvable)
+        })
+        .expect("the network has more than one server for the virus to take over")
+}
+
+fn setup_level(
+    mut commands: Commands,
+    config: Res<LevelConfig>,
+    images: Res<HandleMap<ImageKey>>,
+    atlases: Res<HandleMap<AtlasKey>>,
+) {
+    for x in -FLOOR_RADIUS..FLOOR_RADIUS {
+        for y in -FLOOR_RADIUS..FLOOR_RADIUS {
+            commands.spawn(SpriteBundle {
+                texture: images.get_or_missing(&ImageKey::Tile),
+                transform: get_transform(IVec2::new(x, y), 1.0, -1.0),
+                ..default()
+            });
+        }
+    }
+
+    let (blueprint, tiles, mut rng) = generate_solvable(&config);
+    let routes = route_links(&blueprint, &tiles, config.routing, &mut rng);
+    spawn_blueprint(
+        &mut commands,
+        &images,
+        &atlases,
+        &blueprint,
+        &tiles,
+        routes,
+        &mut rng,
+    );
+    commands.insert_resource(LevelRng(rng));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // no two nodes end up closer than `MIN_SPACING` tiles apart.
+    fn assert_spaced(tiles: &[IVec2]) {
+        for (i, a) in tiles.iter().enumerate() {
+            for b in &tiles[i + 1..] {
+                let spacing = (*a - *b).abs().max_element();
+                assert!(spacing >= MIN_SPACING, "{a} and {b} are too close");
+            }
+        }
+    }
+
+    // how far apart linked nodes end up, on average, as a fraction of how far apart the rest do.
+    fn link_length_ratio(blueprint: &Blueprint, tiles: &[IVec2]) -> f32 {
+        let (mut linked, mut unlinked) = (vec![], vec![]);
+        for a in 0..tiles.len() {
+            for b in a + 1..tiles.len() {
+                let distance = tiles[a].as_vec2().distance(tiles[b].as_vec2());
+                if blueprint.links.contains(&(a, b)) || blueprint.links.contains(&(b, a)) {
+                    linked.push(distance);
+                } else {
+                    unlinked.push(distance);
+                }
+            }
+        }
+        let mean = |distances: &[f32]| distances.iter().sum::<f32>() / distances.len() as f32;
+        mean(&linked) / mean(&unlinked)
+    }
+
+    #[test]
+    fn force_directed_placement_pulls_linked_nodes_together() {
+        for seed in 0..10 {
+            let blueprint = build(&Topology::default(), 2, &mut StdRng::seed_from_u64(seed));
+            let placed = |placement| {
+                let tiles = place(&blueprint, placement, &mut StdRng::seed_from_u64(seed));
+                link_length_ratio(&blueprint, &tiles)
+            };
+            let (force, random) = (placed(Placement::ForceDirected), placed(Placement::Random));
+            assert!(force < 0.7, "linked nodes are {force} as far apart as the rest");
+            assert!(force < random, "random placement did better for seed {seed}");
+        }
+    }
+
+    #[test]
+    fn generated_levels_can_be_won() {
+        for seed in 0..5 {
+            let config = LevelConfig {
+                seed,
+                ..default()
+            };
+            let (blueprint, tiles, _) = generate_solvable(&config);
+            let steps = Puzzle::from_blueprint(&blueprint, &tiles).solve(MAX_STEPS);
+            assert!(steps.is_some_and(|steps| !steps.is_empty()));
+        }
+    }
+
+    #[test]
+    fn levels_are_relaxed_when_no_seed_can_be_won() {
+        // every server starts out hacked, so every level is won before it's begun.
+        let config = LevelConfig {
+            seed: 0,
+            hacked_chance: 1.0,
+            ..default()
+        };
+        let (blueprint, tiles, _) = generate_solvable(&config);
+        assert!(blueprint.hacked.is_empty());
+        assert!(blueprint.firewalls.is_empty());
+        assert!(Puzzle::from_blueprint(&blueprint, &tiles).solve(MAX_STEPS).is_some());
+    }
+
+    #[test]
+    fn snapping_pulls_apart_nodes_in_the_same_place() {
+        let tiles = snap_to_grid(&[Vec2::ZERO; 12]);
+        assert_eq!(tiles.len(), 12);
+        assert_spaced(&tiles);
+    }
+
+    #[test]
+    fn snapping_keeps_spaced_nodes_where_they_are() {
+        let positions = [Vec2::new(0.0, 0.0), Vec2::new(5.2, -0.4), Vec2::new(-3.0, 7.9)];
+        let tiles = snap_to_grid(&positions);
+        assert_eq!(tiles, [IVec2::new(0, 0), IVec2::new(5, 0), IVec2::new(-3, 8)]);
+    }
+}
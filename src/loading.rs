@@ -0,0 +1,138 @@
+use std::{fmt::Debug, hash::Hash};
+
+use bevy::{asset::LoadState, prelude::*, utils::HashSet};
+
+use crate::{
+    assetloader::{HandleMap, ImageKey, SfxKey},
+    manifest::{AppliedSections, AssetManifest, ManifestHandle, ManifestKey},
+};
+
+const TEXT_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_state::<GameState>()
+        .enable_state_scoped_entities::<GameState>()
+        .init_resource::<PendingLoads>()
+        .add_systems(OnEnter(GameState::Loading), setup_loading_screen)
+        .add_systems(
+            Update,
+            (
+                wait_for_manifest,
+                wait_for::<ImageKey>,
+                wait_for::<SfxKey>,
+                finish_loading,
+            )
+                .chain()
+                .run_if(in_state(GameState::Loading)),
+        );
+}
+
+#[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GameState {
+    // waiting for every handle in every `HandleMap` to finish loading.
+    #[default]
+    Loading,
+    Playing,
+}
+
+// handles that haven't finished loading yet, counted up afresh every frame.
+#[derive(Resource, Default)]
+struct PendingLoads(usize);
+
+fn setup_loading_screen(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "loading...",
+            TextStyle {
+                font_size: 16.,
+                color: TEXT_COLOR,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(20.0),
+            top: Val::Px(20.0),
+            ..default()
+        }),
+        StateScoped(GameState::Loading),
+    ));
+}
+
+// count the handles in a `HandleMap` that are still loading, and report any that failed or
+// that the manifest doesn't list. failed handles count as finished, so one bad file doesn't
+// hold up the game.
+fn wait_for<K: ManifestKey + Debug>(
+    handles: Res<HandleMap<K>>,
+    manifest: Res<ManifestHandle>,
+    applied: Res<AppliedSections>,
+    asset_server: Res<AssetServer>,
+    mut pending: ResMut<PendingLoads>,
+    mut reported: Local<HashSet<K>>,
+) {
+    // the map is empty until its section of the manifest has been applied, unless there's no
+    // manifest to apply.
+    let failed = matches!(
+        asset_server.get_load_state(&manifest.0),
+        Some(LoadState::Failed(_))
+    );
+    if !applied.0.contains(K::SECTION) && !failed {
+        pending.0 += 1;
+        return;
+    }
+
+    // without a manifest, everything's missing, and that's been reported already.
+    if !failed {
+        for (name, key) in K::NAMES {
+            if !handles.contains_key(key) && reported.insert(*key) {
+                error!("`{name}` is missing from the [{}] section of the manifest", K::SECTION);
+            }
+        }
+    }
+
+    for (key, handle) in handles.iter() {
+        match asset_server.get_load_state(handle) {
+            Some(LoadState::Loaded) => {}
+            Some(LoadState::Failed(error)) => {
+                if reported.insert(*key) {
+                    let path = handle
+                        .path()
+                        .map_or("<no path>".to_string(), |path| path.to_string());
+                    error!("failed to load {key:?} from {path}: {error}");
+                }
+            }
+            // handles that weren't loaded from a file don't have a load state.
+            None if handle.path().is_none() => {}
+            _ => pending.0 += 1,
+        }
+    }
+}
+
+// the manifest has to load before anything it lists can start loading.
+fn wait_for_manifest(
+    manifest: Res<ManifestHandle>,
+    manifests: Res<Assets<AssetManifest>>,
+    asset_server: Res<AssetServer>,
+    mut pending: ResMut<PendingLoads>,
+    mut reported: Local<bool>,
+) {
+    if manifests.contains(&manifest.0) {
+        return;
+    }
+    match asset_server.get_load_state(&manifest.0) {
+        Some(LoadState::Failed(error)) => {
+            if !*reported {
+                *reported = true;
+                error!("failed to load the asset manifest: {error}");
+            }
+        }
+        _ => pending.0 += 1,
+    }
+}
+
+fn finish_loading(mut pending: ResMut<PendingLoads>, mut next_state: ResMut<NextState<GameState>>) {
+    if pending.0 == 0 {
+        next_state.set(GameState::Playing);
+    }
+    pending.0 = 0;
+}
@@ -0,0 +1,186 @@
+use std::{fmt, hash::Hash};
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext, TrackAssets},
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+use crate::assetloader::{AssetKey, HandleMap, ImageKey, SfxKey};
+
+const MANIFEST_PATH: &str = "assets.manifest";
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_asset::<AssetManifest>()
+        .init_asset_loader::<ManifestLoader>()
+        .init_resource::<ManifestHandle>()
+        .init_resource::<AppliedSections>()
+        .add_systems(
+            PreUpdate,
+            // the manifest is only in `Assets` once bevy has tracked this frame's loads.
+            (apply_manifest::<ImageKey>, apply_manifest::<SfxKey>).after(TrackAssets),
+        );
+}
+
+// asset keys that get their paths from a section of the manifest.
+pub trait ManifestKey: AssetKey + Copy + Eq + Hash + Send + Sync + 'static {
+    // the `[section]` of the manifest these keys are listed under.
+    const SECTION: &'static str;
+
+    // every key, by the name it's listed under.
+    const NAMES: &'static [(&'static str, Self)];
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::NAMES
+            .iter()
+            .find(|(key_name, _)| *key_name == name)
+            .map(|&(_, key)| key)
+    }
+}
+
+// maps asset keys to the files they're loaded from, grouped into sections by key type.
+#[derive(Asset, TypePath, Debug, Default)]
+pub struct AssetManifest {
+    pub sections: HashMap<String, HashMap<String, String>>,
+}
+
+impl AssetManifest {
+    // parse `key = path` lines under `[section]` headers. blank lines and `#` comments are skipped.
+    fn parse(text: &str) -> Result<Self, ManifestError> {
+        let mut manifest = AssetManifest::default();
+        let mut section = None;
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = Some(name.trim().to_string());
+                continue;
+            }
+
+            let syntax_error = || ManifestError::Syntax {
+                line: i + 1,
+                text: line.to_string(),
+            };
+            let (Some(section), Some((key, path))) = (&section, line.split_once('=')) else {
+                return Err(syntax_error());
+            };
+            manifest
+                .sections
+                .entry(section.clone())
+                .or_default()
+                .insert(key.trim().to_string(), path.trim().to_string());
+        }
+        Ok(manifest)
+    }
+}
+
+#[derive(Debug)]
+pub enum ManifestError {
+    Io(std::io::Error),
+    Utf8(std::str::Utf8Error),
+    Syntax { line: usize, text: String },
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ManifestError::Io(error) => write!(f, "couldn't read manifest: {error}"),
+            ManifestError::Utf8(error) => write!(f, "manifest isn't utf-8: {error}"),
+            ManifestError::Syntax { line, text } => {
+                write!(f, "expected `[section]` or `key = path` on line {line}: {text}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+impl From<std::io::Error> for ManifestError {
+    fn from(error: std::io::Error) -> Self {
+        ManifestError::Io(error)
+    }
+}
+
+impl From<std::str::Utf8Error> for ManifestError {
+    fn from(error: std::str::Utf8Error) -> Self {
+        ManifestError::Utf8(error)
+    }
+}
+
+#[derive(Default)]
+struct ManifestLoader;
+
+impl AssetLoader for ManifestLoader {
+    type Asset = AssetManifest;
+    type Settings = ();
+    type Error = ManifestError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<AssetManifest, ManifestError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        AssetManifest::parse(std::str::from_utf8(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["manifest"]
+    }
+}
+
+#[derive(Resource)]
+pub struct ManifestHandle(pub Handle<AssetManifest>);
+
+impl FromWorld for ManifestHandle {
+    fn from_world(world: &mut World) -> Self {
+        Self(world.resource::<AssetServer>().load(MANIFEST_PATH))
+    }
+}
+
+// the sections of the manifest that have been applied to their `HandleMap`s.
+#[derive(Resource, Default)]
+pub struct AppliedSections(pub HashSet<&'static str>);
+
+// (re)load every handle in a `HandleMap` from its section of the manifest. this runs once the
+// manifest first loads, and again whenever it's edited.
+fn apply_manifest<K: ManifestKey>(
+    mut events: EventReader<AssetEvent<AssetManifest>>,
+    manifest: Res<ManifestHandle>,
+    manifests: Res<Assets<AssetManifest>>,
+    asset_server: Res<AssetServer>,
+    mut handles: ResMut<HandleMap<K>>,
+    mut applied: ResMut<AppliedSections>,
+) {
+    let modified = events
+        .read()
+        .filter(|ev| ev.is_modified(&manifest.0))
+        .count()
+        > 0;
+    if applied.0.contains(K::SECTION) && !modified {
+        return;
+    }
+    let Some(manifest) = manifests.get(&manifest.0) else {
+        return;
+    };
+
+    let mut loaded: HashMap<K, Handle<K::Asset>> = HashMap::default();
+    for (name, path) in manifest.sections.get(K::SECTION).into_iter().flatten() {
+        match K::from_name(name) {
+            Some(key) => {
+                loaded.insert(key, asset_server.load(path.clone()));
+            }
+            None => warn!("unknown key `{name}` in the [{}] section of the manifest", K::SECTION),
+        }
+    }
+    // a section that isn't in the manifest leaves the map alone, so it isn't marked as changed.
+    if !loaded.is_empty() || modified {
+        *handles = loaded.into();
+    }
+    applied.0.insert(K::SECTION);
+}
@@ -0,0 +1,230 @@
+use bevy::{
+    color::palettes::css::{
+        AQUA, BLUE, DARK_GRAY, GRAY, GREEN, LIME, ORANGE, PURPLE, RED, WHITE, YELLOW,
+    },
+    prelude::*,
+    render::{
+        camera::RenderTarget,
+        render_resource::{
+            Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+        },
+        view::RenderLayers,
+    },
+    ui::RelativeCursorPosition,
+};
+
+use crate::{
+    firewall::Firewall,
+    rendering::{InGameCamera, RES_HEIGHT, RES_WIDTH},
+    server::{Battery, Cooler, Generator, Server, ServerState, Switch},
+    wire::Wire,
+    TILE_SIZE,
+};
+
+// size of the minimap on screen, and of the texture it's rendered to.
+const MINIMAP_SIZE: u32 = 160;
+
+// width and height of the patch of world the minimap shows, centred on the origin.
+const MAP_EXTENT: f32 = 100.0 * TILE_SIZE;
+
+// render layers only the minimap camera looks at.
+const MINIMAP_LAYERS: RenderLayers = RenderLayers::layer(2);
+
+const BACKGROUND_COLOR: Color = Color::srgb(0.05, 0.05, 0.05);
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_gizmo_group::<MinimapGizmos>()
+        .add_systems(Startup, setup_minimap)
+        .add_systems(Update, (draw_minimap, jump_to_click));
+}
+
+// gizmos drawn onto the minimap rather than the game world.
+#[derive(Default, Reflect, GizmoConfigGroup)]
+struct MinimapGizmos;
+
+#[derive(Component)]
+struct Minimap;
+
+fn setup_minimap(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut config_store: ResMut<GizmoConfigStore>,
+) {
+    let (config, _) = config_store.config_mut::<MinimapGizmos>();
+    config.render_layers = MINIMAP_LAYERS;
+
+    let size = Extent3d {
+        width: MINIMAP_SIZE,
+        height: MINIMAP_SIZE,
+        ..default()
+    };
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: None,
+            size,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Bgra8UnormSrgb,
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_DST
+                | TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        },
+        ..default()
+    };
+    image.resize(size);
+    let image_handle = images.add(image);
+
+    // this camera draws the whole map, shrunk down, into the minimap texture
+    commands.spawn((
+        Camera2dBundle {
+            camera: Camera {
+                order: -2,
+                target: RenderTarget::Image(image_handle.clone()),
+                clear_color: ClearColorConfig::Custom(BACKGROUND_COLOR),
+                ..default()
+            },
+            projection: OrthographicProjection {
+                scale: MAP_EXTENT / MINIMAP_SIZE as f32,
+                ..default()
+            },
+            ..default()
+        },
+        MINIMAP_LAYERS,
+    ));
+
+    commands.spawn((
+        ImageBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                left: Val::Px(20.0),
+                top: Val::Px(60.0),
+                width: Val::Px(MINIMAP_SIZE as f32),
+                height: Val::Px(MINIMAP_SIZE as f32),
+                ..default()
+            },
+            image: UiImage::new(image_handle),
+            ..default()
+        },
+        Interaction::default(),
+        RelativeCursorPosition::default(),
+        Minimap,
+    ));
+}
+
+fn state_color(state: ServerState) -> Srgba {
+    match state {
+        ServerState::Healthy => LIME,
+        ServerState::Hacked => YELLOW,
+        ServerState::Infected => PURPLE,
+        ServerState::Broken => DARK_GRAY,
+        ServerState::Off => GRAY,
+    }
+}
+
+fn draw_minimap(
+    mut gizmos: Gizmos<MinimapGizmos>,
+    servers: Query<(&Transform, &Server)>,
+    generators: Query<&Transform, With<Generator>>,
+    batteries: Query<(&Transform, &Battery)>,
+    switches: Query<(&Transform, &Switch)>,
+    coolers: Query<(&Transform, &Cooler)>,
+    terminals: Query<&Transform>,
+    wires: Query<(&Wire, Option<&Firewall>)>,
+    camera: Query<&Transform, With<InGameCamera>>,
+) {
+    for (wire, firewall) in &wires {
+        let Ok([a, b]) = terminals.get_many(wire.terminals) else {
+            continue;
+        };
+
+        let is_pwr = wire
+            .terminals
+            .iter()
+            .any(|terminal| generators.contains(*terminal) || batteries.contains(*terminal));
+        let color = if !wire.cuts.is_empty() {
+            DARK_GRAY
+        } else if is_pwr {
+            RED
+        } else if firewall.is_some() {
+            ORANGE
+        } else {
+            BLUE
+        };
+        gizmos.line_2d(a.translation.truncate(), b.translation.truncate(), color);
+    }
+
+    for transform in &generators {
+        gizmos.rect_2d(
+            transform.translation.truncate(),
+            0.0,
+            Vec2::splat(3.0 * TILE_SIZE),
+            ORANGE,
+        );
+    }
+
+    for (transform, battery) in &batteries {
+        gizmos.rect_2d(
+            transform.translation.truncate(),
+            0.0,
+            Vec2::splat(2.0 * TILE_SIZE),
+            if battery.live() { GREEN } else { GRAY },
+        );
+    }
+
+    // coolers show how far they reach while they're on.
+    for (transform, cooler) in &coolers {
+        let position = transform.translation.truncate();
+        let color = if cooler.on() { AQUA } else { GRAY };
+        gizmos.rect_2d(position, 0.0, Vec2::splat(2.0 * TILE_SIZE), color);
+        if cooler.on() {
+            gizmos.circle_2d(position, cooler.radius * TILE_SIZE, color.with_alpha(0.3));
+        }
+    }
+
+    for (transform, switch) in &switches {
+        gizmos.rect_2d(
+            transform.translation.truncate(),
+            0.0,
+            Vec2::splat(2.0 * TILE_SIZE),
+            if switch.on() { WHITE } else { GRAY },
+        );
+    }
+
+    for (transform, server) in &servers {
+        gizmos.circle_2d(
+            transform.translation.truncate(),
+            2.0 * TILE_SIZE,
+            state_color(server.state),
+        );
+    }
+
+    let view = camera.single().translation.truncate();
+    gizmos.rect_2d(
+        view,
+        0.0,
+        Vec2::new(RES_WIDTH as f32, RES_HEIGHT as f32),
+        WHITE,
+    );
+}
+
+// centre the view on wherever the minimap is clicked, or dragged across.
+fn jump_to_click(
+    minimap: Query<(&Interaction, &RelativeCursorPosition), With<Minimap>>,
+    mut camera: Query<&mut Transform, With<InGameCamera>>,
+) {
+    let (interaction, cursor) = minimap.single();
+    if *interaction != Interaction::Pressed {
+        return;
+    }
+    let Some(normalized) = cursor.normalized else {
+        return;
+    };
+
+    // ui coordinates go down from the top left, world coordinates go up from the centre.
+    let target = Vec2::new(normalized.x - 0.5, 0.5 - normalized.y) * MAP_EXTENT;
+    let mut transform = camera.single_mut();
+    transform.translation.x = target.x;
+    transform.translation.y = target.y;
+}
@@ -1,6 +1,6 @@
 use bevy::{prelude::*, window::PrimaryWindow};
 
-use crate::rendering::InGameCamera;
+use crate::rendering::{InGameCamera, OuterCamera};
 
 #[derive(Resource, Default)]
 pub struct MouseWorldCoords(pub Vec2);
@@ -9,15 +9,20 @@ pub struct MouseWorldCoords(pub Vec2);
 pub fn mouse_world_coords(
     mut mouse_coords: ResMut<MouseWorldCoords>,
     window: Query<&Window, With<PrimaryWindow>>,
-    camera: Query<(&Camera, &GlobalTransform), With<InGameCamera>>,
+    outer_camera: Query<(&Camera, &GlobalTransform), With<OuterCamera>>,
+    in_game_camera: Query<&GlobalTransform, With<InGameCamera>>,
 ) {
-    let (camera, camera_transform) = camera.single();
-    let window = window.single();
-    if let Some(world_position) = window
+    // the outer camera sees the canvas centred on the origin, one world unit per canvas pixel,
+    // so a point on the canvas is just an offset from wherever the in-game camera is looking.
+    // there's no window when running headless.
+    let Ok(window) = window.get_single() else {
+        return;
+    };
+    let (camera, camera_transform) = outer_camera.single();
+    if let Some(canvas_position) = window
         .cursor_position()
-        .and_then(|cursor| camera.viewport_to_world(camera_transform, cursor))
-        .map(|ray| ray.origin.truncate())
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor))
     {
-        mouse_coords.0 = world_position;
+        mouse_coords.0 = canvas_position + in_game_camera.single().translation().truncate();
     }
 }
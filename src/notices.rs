@@ -0,0 +1,110 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::{loading::GameState, text_input::Rejected};
+
+const TEXT_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
+const REJECTED_COLOR: Color = Color::srgb(1.0, 0.45, 0.4);
+const BACKGROUND_COLOR: Color = Color::srgba(0.15, 0.15, 0.15, 0.8);
+
+// how many notices are shown at once. older ones drop off the top.
+const MAX_NOTICES: usize = 6;
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_event::<Notice>()
+        .init_resource::<NoticeLog>()
+        .add_systems(Startup, setup_notice_log)
+        .add_systems(OnEnter(GameState::Playing), clear_notices)
+        .add_systems(Update, (collect_notices, update_notice_log).chain());
+}
+
+// something the player should hear about, shown in the log above the command line along with
+// the reasons commands are rejected.
+#[derive(Event)]
+pub struct Notice(pub String);
+
+// the latest notices and rejections, oldest first, and whether each was a rejection.
+#[derive(Resource, Default)]
+struct NoticeLog(VecDeque<(String, bool)>);
+
+#[derive(Component)]
+struct NoticePanel;
+
+fn setup_notice_log(mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                left: Val::Px(20.0),
+                bottom: Val::Px(52.0),
+                width: Val::Px(400.0),
+                padding: UiRect::all(Val::Px(4.0)),
+                ..default()
+            },
+            background_color: BACKGROUND_COLOR.into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((TextBundle::default(), NoticePanel));
+        });
+}
+
+fn clear_notices(mut log: ResMut<NoticeLog>) {
+    log.0.clear();
+}
+
+fn collect_notices(
+    mut notices: EventReader<Notice>,
+    mut rejected: EventReader<Rejected>,
+    mut log: ResMut<NoticeLog>,
+) {
+    let notices = notices.read().map(|Notice(text)| (text.clone(), false));
+    let rejected = rejected.read().map(|Rejected(reason)| (reason.clone(), true));
+    for entry in notices.chain(rejected) {
+        log.0.push_back(entry);
+        if log.0.len() > MAX_NOTICES {
+            log.0.pop_front();
+        }
+    }
+}
+
+fn update_notice_log(
+    log: Res<NoticeLog>,
+    mut panel: Query<(&mut Text, &Parent), With<NoticePanel>>,
+    mut visibility: Query<&mut Visibility>,
+) {
+    if !log.is_changed() {
+        return;
+    }
+    let Ok((mut text, parent)) = panel.get_single_mut() else {
+        return;
+    };
+
+    text.sections = log
+        .0
+        .iter()
+        .enumerate()
+        .map(|(i, (line, rejection))| {
+            let line = if i + 1 < log.0.len() { format!("{line}\n") } else { line.clone() };
+            let color = if *rejection { REJECTED_COLOR } else { TEXT_COLOR };
+            TextSection::new(
+                line,
+                TextStyle {
+                    font_size: 14.,
+                    color,
+                    ..default()
+                },
+            )
+        })
+        .collect();
+
+    // the panel is hidden while there's nothing to show.
+    if let Ok(mut visibility) = visibility.get_mut(parent.get()) {
+        *visibility = if log.0.is_empty() {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+    }
+}
@@ -0,0 +1,372 @@
+use bevy::prelude::*;
+use itertools::Itertools;
+
+use crate::{
+    clock::{Clock, Tick},
+    economy::{cost, Compute},
+    notices::Notice,
+    server::{
+        find_server, name_of, relay, Battery, Cooler, Generator, Server, ServerProgram,
+        ServerState, Switch,
+    },
+    text_input::{Action, Rejected},
+    upgrades::{Upgrade, Upgrades},
+    wire::{connected, linked, Wire},
+};
+
+// how often a cycling server power-cycles.
+const CYCLE_PERIOD: usize = 32;
+
+// heat a power spike adds to a generator, per unit of power the cycled server draws.
+const SPIKE_HEAT: f32 = 4.0;
+
+// heat a generator gains each tick, per unit of load over its `overload`.
+const OVERLOAD_HEAT: f32 = 0.05;
+
+// heat a generator loses each tick.
+const GENERATOR_COOLING: f32 = 0.02;
+
+// an overheated generator starts back up once its heat drops below this fraction of `overheat`.
+const RESTART_FRACTION: f32 = 0.5;
+
+// power a battery draws from its generator while it's charging, and the charge it gains each tick.
+const CHARGE_POWER: f32 = 0.5;
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_event::<PowerLost>()
+        .add_event::<PowerRestored>()
+        .add_event::<GeneratorOverheated>()
+        .add_event::<GeneratorRestarted>()
+        .add_systems(Update, (start_cycles, report_power))
+        .add_systems(
+            Tick,
+            (
+                cycle_servers,
+                heat_generators,
+                supply_batteries,
+                supply_power,
+                supply_switches,
+                supply_coolers,
+                drain_batteries,
+            )
+                .chain(),
+        );
+}
+
+#[derive(Event)]
+pub struct PowerLost {
+    pub server: Entity,
+    // whether the virus was running on the server, and so has been killed.
+    pub virus_killed: bool,
+}
+
+#[derive(Event)]
+pub struct PowerRestored {
+    pub server: Entity,
+}
+
+#[derive(Event)]
+pub struct GeneratorOverheated {
+    pub generator: Entity,
+}
+
+#[derive(Event)]
+pub struct GeneratorRestarted {
+    pub generator: Entity,
+}
+
+// run `msg <server> cycle` commands. with the cycle upgrade, servers wired to one the
+// virus controls can be cycled remotely.
+fn start_cycles(
+    mut actions: EventReader<Action>,
+    mut rejected: EventWriter<Rejected>,
+    mut servers: Query<(Entity, &mut Server)>,
+    wires: Query<&Wire>,
+    switches: Query<&Switch>,
+    upgrades: Res<Upgrades>,
+    mut compute: ResMut<Compute>,
+) {
+    for action in actions.read() {
+        let Action::Cycle { server: name } = action else {
+            continue;
+        };
+
+        let Some(entity) = find_server(&servers, name) else {
+            rejected.send(Rejected(format!("no server named {name}")));
+            continue;
+        };
+
+        let remote = upgrades.level(Upgrade::Cycle) > 0
+            && servers
+                .iter()
+                .any(|(other, server)| {
+                    server.controlled() && connected(&wires, |e| relay(&switches, e), entity, other)
+                });
+
+        let Ok((_, mut server)) = servers.get_mut(entity) else {
+            continue;
+        };
+
+        if !server.controlled() && !remote {
+            rejected.send(Rejected(format!("{name} isn't under the virus' control")));
+            continue;
+        }
+
+        if !compute.spend(cost(action)) {
+            rejected.send(Rejected(format!("not enough compute to run that on {name}")));
+            continue;
+        }
+
+        server.program = ServerProgram::Cycle;
+    }
+}
+
+// every so often, each cycling server spikes the generator it draws from, even through a battery.
+// the phase goes by the level's clock, so that it's the same when a level is replayed or rewound.
+fn cycle_servers(
+    clock: Res<Clock>,
+    servers: Query<&Server>,
+    batteries: Query<&Battery>,
+    mut generators: Query<&mut Generator>,
+) {
+    // `elapsed` is only counted up once the tick is over.
+    if !(clock.elapsed + 1).is_multiple_of(CYCLE_PERIOD) {
+        return;
+    }
+
+    for server in &servers {
+        if server.program != ServerProgram::Cycle {
+            continue;
+        }
+        let generator = server
+            .supply
+            .and_then(|supply| batteries.get(supply).map_or(Some(supply), |b| b.grid));
+        if let Some(mut generator) = generator.and_then(|g| generators.get_mut(g).ok()) {
+            generator.heat += server.power * SPIKE_HEAT;
+        }
+    }
+}
+
+// work out each generator's load, and heat it up if it's overloaded.
+// generators that get too hot shut down until they cool off.
+fn heat_generators(
+    mut generators: Query<(Entity, &mut Generator)>,
+    servers: Query<&Server>,
+    switches: Query<&Switch>,
+    coolers: Query<&Cooler>,
+    batteries: Query<(Entity, &Battery)>,
+    mut ev_overheated: EventWriter<GeneratorOverheated>,
+    mut ev_restarted: EventWriter<GeneratorRestarted>,
+) {
+    // what each node draws, including everything downstream of batteries on the grid.
+    let draw = |supply: Entity| {
+        servers
+            .iter()
+            .filter(|server| server.supply == Some(supply) && server.state != ServerState::Off)
+            .map(|server| server.power)
+            .sum::<f32>()
+    };
+
+    for (entity, mut generator) in &mut generators {
+        generator.load = draw(entity)
+            + batteries
+                .iter()
+                .filter(|(_, battery)| battery.grid == Some(entity))
+                .map(|(battery_entity, battery)| {
+                    let charging = if battery.charge < battery.capacity {
+                        CHARGE_POWER
+                    } else {
+                        0.0
+                    };
+                    draw(battery_entity) + charging
+                })
+                .sum::<f32>()
+            + switches
+                .iter()
+                .filter(|switch| switch.supply == Some(entity))
+                .map(|switch| switch.power)
+                .sum::<f32>()
+            + coolers
+                .iter()
+                .filter(|cooler| cooler.supply == Some(entity))
+                .map(|cooler| cooler.power)
+                .sum::<f32>();
+
+        if generator.online {
+            generator.heat += (generator.load - generator.overload).max(0.0) * OVERLOAD_HEAT;
+        }
+        generator.heat = (generator.heat - GENERATOR_COOLING).max(0.0);
+
+        if generator.online && generator.heat > generator.overheat {
+            generator.online = false;
+            ev_overheated.send(GeneratorOverheated { generator: entity });
+        } else if !generator.online && generator.heat < generator.overheat * RESTART_FRACTION {
+            generator.online = true;
+            ev_restarted.send(GeneratorRestarted { generator: entity });
+        }
+    }
+}
+
+// the online generator `entity` is wired to, sticking with `current` while it's still good.
+fn find_generator(
+    entity: Entity,
+    current: Option<Entity>,
+    generators: &Query<(Entity, &Generator)>,
+    wires: &Query<&Wire>,
+) -> Option<Entity> {
+    let online = |generator: Entity| {
+        generators.get(generator).is_ok_and(|(_, g)| g.online) && linked(wires, entity, generator)
+    };
+    current.filter(|&current| online(current)).or_else(|| {
+        generators
+            .iter()
+            .map(|(generator, _)| generator)
+            .find(|&generator| online(generator))
+    })
+}
+
+// charge every battery from an online generator it's wired to, if there is one.
+fn supply_batteries(
+    mut batteries: Query<(Entity, &mut Battery)>,
+    generators: Query<(Entity, &Generator)>,
+    wires: Query<&Wire>,
+) {
+    for (entity, mut battery) in &mut batteries {
+        battery.grid = find_generator(entity, battery.grid, &generators, &wires);
+    }
+}
+
+// hook every server up to power. servers stick with the generator they have while it lasts,
+// then fail over to another generator they're wired to, then to a battery. servers with nothing
+// left turn off, losing whatever was running on them, and come back healthy once power returns.
+fn supply_power(
+    mut servers: Query<(Entity, &mut Server)>,
+    generators: Query<(Entity, &Generator)>,
+    batteries: Query<(Entity, &Battery)>,
+    wires: Query<&Wire>,
+    mut ev_lost: EventWriter<PowerLost>,
+    mut ev_restored: EventWriter<PowerRestored>,
+) {
+    for (entity, mut server) in &mut servers {
+        let battery = |battery: Entity| {
+            batteries.get(battery).is_ok_and(|(_, b)| b.live()) && linked(&wires, entity, battery)
+        };
+        server.supply = find_generator(entity, server.supply, &generators, &wires)
+            .or_else(|| server.supply.filter(|&supply| battery(supply)))
+            .or_else(|| {
+                // batteries still on the grid first, so the others keep their charge.
+                batteries
+                    .iter()
+                    .filter(|&(b, _)| battery(b))
+                    .max_by_key(|(_, b)| b.grid.is_some())
+                    .map(|(b, _)| b)
+            });
+
+        match (server.supply, server.state) {
+            (_, ServerState::Broken) => {}
+            // a reboot keeps counting down without power.
+            (None, ServerState::Off) if !matches!(server.program, ServerProgram::Reboot(_)) => {
+                server.program = ServerProgram::Wait;
+            }
+            (None, ServerState::Off) => {}
+            (None, state) => {
+                server.state = ServerState::Off;
+                server.program = ServerProgram::Wait;
+                ev_lost.send(PowerLost {
+                    server: entity,
+                    virus_killed: state == ServerState::Infected,
+                });
+            }
+            (Some(_), ServerState::Off) if !matches!(server.program, ServerProgram::Reboot(_)) => {
+                server.state = ServerState::Healthy;
+                ev_restored.send(PowerRestored { server: entity });
+            }
+            _ => {}
+        }
+    }
+}
+
+// switches draw power just like servers, and stop relaying without it.
+fn supply_switches(
+    mut switches: Query<(Entity, &mut Switch)>,
+    generators: Query<(Entity, &Generator)>,
+    wires: Query<&Wire>,
+) {
+    for (entity, mut switch) in &mut switches {
+        switch.supply = find_generator(entity, switch.supply, &generators, &wires);
+    }
+}
+
+// coolers stop cooling without power.
+fn supply_coolers(
+    mut coolers: Query<(Entity, &mut Cooler)>,
+    generators: Query<(Entity, &Generator)>,
+    wires: Query<&Wire>,
+) {
+    for (entity, mut cooler) in &mut coolers {
+        cooler.supply = find_generator(entity, cooler.supply, &generators, &wires);
+    }
+}
+
+// charge batteries on the grid, and drain the ones off it by what their servers draw.
+fn drain_batteries(mut batteries: Query<(Entity, &mut Battery)>, servers: Query<&Server>) {
+    for (entity, mut battery) in &mut batteries {
+        if battery.grid.is_some() {
+            battery.charge = (battery.charge + CHARGE_POWER).min(battery.capacity);
+            continue;
+        }
+
+        let draw: f32 = servers
+            .iter()
+            .filter(|server| server.supply == Some(entity) && server.state != ServerState::Off)
+            .map(|server| server.power)
+            .sum();
+        battery.charge = (battery.charge - draw).max(0.0);
+    }
+}
+
+// tell the player when servers lose or get back their power, and when generators go down or
+// come back. generators don't have names, so they go by the servers wired to them.
+fn report_power(
+    mut ev_lost: EventReader<PowerLost>,
+    mut ev_restored: EventReader<PowerRestored>,
+    mut ev_overheated: EventReader<GeneratorOverheated>,
+    mut ev_restarted: EventReader<GeneratorRestarted>,
+    mut notices: EventWriter<Notice>,
+    servers: Query<&Server>,
+    wires: Query<&Wire>,
+) {
+    let generator = |entity: Entity| {
+        let powering = wires
+            .iter()
+            .filter_map(|wire| match wire.terminals {
+                [a, b] | [b, a] if a == entity => servers.get(b).ok(),
+                _ => None,
+            })
+            .map(|server| server.name.as_str())
+            .join(", ");
+        if powering.is_empty() {
+            "a generator".to_string()
+        } else {
+            format!("the generator powering {powering}")
+        }
+    };
+
+    for ev in ev_lost.read() {
+        let server = name_of(&servers, ev.server);
+        notices.send(Notice(if ev.virus_killed {
+            format!("{server} lost power, taking the virus running on it down with it")
+        } else {
+            format!("{server} lost power")
+        }));
+    }
+    for ev in ev_restored.read() {
+        notices.send(Notice(format!("{} has power again", name_of(&servers, ev.server))));
+    }
+    for ev in ev_overheated.read() {
+        notices.send(Notice(format!("{} overheated and shut down", generator(ev.generator))));
+    }
+    for ev in ev_restarted.read() {
+        notices.send(Notice(format!("{} started back up", generator(ev.generator))));
+    }
+}
@@ -0,0 +1,119 @@
+use bevy::prelude::*;
+
+use crate::{
+    clock::Tick,
+    notices::Notice,
+    server::{find_server, name_of, Server, ServerProgram, ServerState},
+    text_input::{Action, Rejected},
+};
+
+// how long a server stays off while rebooting.
+pub const REBOOT_TICKS: usize = 256;
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_event::<RebootStarted>()
+        .add_event::<RebootFinished>()
+        .add_systems(Update, (start_reboots, report_reboots))
+        .add_systems(Tick, count_down_reboots);
+}
+
+#[derive(Event)]
+pub struct RebootStarted {
+    pub server: Entity,
+    // whether the virus asked for the reboot, rather than the defender.
+    pub by_virus: bool,
+    // whether the virus was running on the server, and so has been killed.
+    pub virus_killed: bool,
+}
+
+#[derive(Event)]
+pub struct RebootFinished {
+    pub server: Entity,
+}
+
+// run `msg <server> reboot` commands. rebooting a hacked server loses it, but
+// draws the defender's attention away from the rest of the network.
+fn start_reboots(
+    mut actions: EventReader<Action>,
+    mut rejected: EventWriter<Rejected>,
+    mut servers: Query<(Entity, &mut Server)>,
+    mut ev_started: EventWriter<RebootStarted>,
+) {
+    for action in actions.read() {
+        let Action::Reboot { server: name } = action else {
+            continue;
+        };
+
+        let Some(entity) = find_server(&servers, name) else {
+            rejected.send(Rejected(format!("no server named {name}")));
+            continue;
+        };
+
+        let Ok((_, mut server)) = servers.get_mut(entity) else {
+            continue;
+        };
+
+        match server.state {
+            ServerState::Hacked => {
+                let virus_killed = server.reboot(REBOOT_TICKS);
+                ev_started.send(RebootStarted {
+                    server: entity,
+                    by_virus: true,
+                    virus_killed,
+                });
+            }
+            ServerState::Infected => {
+                rejected.send(Rejected(format!(
+                    "the virus is running on {name}, rebooting it would kill the virus"
+                )));
+            }
+            _ => {
+                rejected.send(Rejected(format!("{name} isn't under the virus' control")));
+            }
+        }
+    }
+}
+
+// tick rebooting servers down, bringing them back up healthy once they hit zero. a server
+// rebooted for n ticks is off for exactly n ticks.
+fn count_down_reboots(
+    mut servers: Query<(Entity, &mut Server)>,
+    mut ev_finished: EventWriter<RebootFinished>,
+) {
+    for (entity, mut server) in &mut servers {
+        let ServerProgram::Reboot(remaining) = server.program else {
+            continue;
+        };
+
+        let remaining = remaining.saturating_sub(1);
+        if remaining > 0 {
+            server.program = ServerProgram::Reboot(remaining);
+            continue;
+        }
+
+        server.state = ServerState::Healthy;
+        server.program = ServerProgram::Wait;
+        ev_finished.send(RebootFinished { server: entity });
+    }
+}
+
+// tell the player about reboots, especially the ones that cost the virus an instance.
+fn report_reboots(
+    mut ev_started: EventReader<RebootStarted>,
+    mut ev_finished: EventReader<RebootFinished>,
+    mut notices: EventWriter<Notice>,
+    servers: Query<&Server>,
+) {
+    for ev in ev_started.read() {
+        let server = name_of(&servers, ev.server);
+        let notice = match (ev.by_virus, ev.virus_killed) {
+            (_, true) => format!("rebooting {server} killed the virus running on it"),
+            (true, false) => format!("{server} is rebooting"),
+            (false, false) => format!("the defender is rebooting {server}"),
+        };
+        notices.send(Notice(notice));
+    }
+    for ev in ev_finished.read() {
+        notices.send(Notice(format!("{} finished rebooting", name_of(&servers, ev.server))));
+    }
+}
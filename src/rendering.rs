@@ -12,14 +12,14 @@ use bevy::{
 };
 
 // In-game resolution width.
-const RES_WIDTH: u32 = 480;
+pub const RES_WIDTH: u32 = 480;
 
 // In-game resolution height.
-const RES_HEIGHT: u32 = 270;
+pub const RES_HEIGHT: u32 = 270;
 
 // Default render layers for pixel-perfect rendering.
 // You can skip adding this component, as this is the default.
-const PIXEL_PERFECT_LAYERS: RenderLayers = RenderLayers::layer(0);
+pub const PIXEL_PERFECT_LAYERS: RenderLayers = RenderLayers::layer(0);
 
 // Render layers for high-resolution rendering.
 const HIGH_RES_LAYERS: RenderLayers = RenderLayers::layer(1);
@@ -0,0 +1,283 @@
+use std::{collections::VecDeque, fmt, fs, path::PathBuf};
+
+use bevy::{app::AppExit, prelude::*};
+
+use crate::{
+    args::Args,
+    clock::{run_ticks, tick, Clock},
+    level::LevelConfig,
+    loading::GameState,
+    text_input::{parse, Action},
+    virus::Outcome,
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<ReplayOptions>()
+        .add_systems(Startup, (start_recording, start_playback))
+        .add_systems(
+            PreUpdate,
+            play_back
+                .after(run_ticks)
+                .run_if(in_state(GameState::Playing))
+                .run_if(resource_exists::<Playback>),
+        )
+        .add_systems(
+            Update,
+            (record_actions.run_if(resource_exists::<Recorder>), report_outcome),
+        )
+        .add_systems(Last, save_on_exit.run_if(resource_exists::<Recorder>));
+}
+
+// what to record or play back, from the command line.
+#[derive(Resource, Default, Clone, Debug)]
+pub struct ReplayOptions {
+    // `--record <file>`: write the seed and every command to this file as the game is played.
+    pub record: Option<PathBuf>,
+    // `--replay <file>`: play back a recording instead of taking commands. the level options
+    // on the command line have to match the ones it was recorded with.
+    pub replay: Option<PathBuf>,
+    // `--headless`: play back without a window, as fast as possible, and quit at the end.
+    pub headless: bool,
+}
+
+impl ReplayOptions {
+    pub fn from_args(args: &mut Args) -> Self {
+        let mut options = ReplayOptions {
+            record: args.value("record").map(PathBuf::from),
+            replay: args.value("replay").map(PathBuf::from),
+            headless: args.flag("headless"),
+        };
+
+        if options.headless && options.replay.is_none() {
+            eprintln!("--headless only works with --replay, ignoring it");
+            options.headless = false;
+        }
+        options
+    }
+}
+
+// a game as it was played: the seed the level was generated from, and every command along with
+// the tick it was carried out on. the same seed and commands always play out the same way.
+//
+// recordings are saved as text, one line each for the seed, every command and the tick the
+// recording ends on:
+//
+//     seed 1234
+//     0 mode turns
+//     64 msg a hack b
+//     end 192
+#[derive(Clone, Debug, Default)]
+pub struct Recording {
+    pub seed: u64,
+    pub commands: Vec<(usize, Action)>,
+    pub end: usize,
+}
+
+impl Recording {
+    pub fn parse(text: &str) -> Result<Recording, String> {
+        let mut recording = Recording::default();
+        let mut seed = None;
+        for (number, line) in text.lines().enumerate().map(|(i, line)| (i + 1, line.trim())) {
+            if line.is_empty() {
+                continue;
+            }
+            let bad = |reason: String| format!("line {number}: {reason}");
+            let (first, rest) = line.split_once(' ').unwrap_or((line, ""));
+            match first {
+                "seed" => {
+                    let parsed = rest.parse().map_err(|_| bad(format!("{rest} isn't a seed")))?;
+                    seed = Some(parsed);
+                }
+                "end" => {
+                    let parsed = rest.parse().map_err(|_| bad(format!("{rest} isn't a tick")))?;
+                    recording.end = parsed;
+                }
+                tick => {
+                    let tick = tick.parse().map_err(|_| bad(format!("{tick} isn't a tick")))?;
+                    let action = parse(rest).map_err(bad)?;
+                    recording.commands.push((tick, action));
+                }
+            }
+        }
+
+        recording.seed = seed.ok_or("the recording doesn't have a seed")?;
+        if !recording.commands.is_sorted_by_key(|(tick, _)| *tick) {
+            return Err("the commands in the recording are out of order".to_string());
+        }
+        // recordings that were cut short end with their last command.
+        let last = recording.commands.last().map_or(0, |(tick, _)| *tick);
+        recording.end = recording.end.max(last);
+        Ok(recording)
+    }
+}
+
+impl fmt::Display for Recording {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "seed {}", self.seed)?;
+        for (tick, action) in &self.commands {
+            writeln!(f, "{tick} {action}")?;
+        }
+        writeln!(f, "end {}", self.end)
+    }
+}
+
+// the game being recorded, and where to save it.
+#[derive(Resource)]
+struct Recorder {
+    path: PathBuf,
+    recording: Recording,
+}
+
+impl Recorder {
+    fn save(&mut self, end: usize) {
+        self.recording.end = end;
+        if let Err(err) = fs::write(&self.path, self.recording.to_string()) {
+            error!("couldn't save the recording to {}: {err}", self.path.display());
+        }
+    }
+}
+
+// the commands still to be played back.
+#[derive(Resource)]
+struct Playback {
+    commands: VecDeque<(usize, Action)>,
+    end: usize,
+}
+
+fn start_recording(mut commands: Commands, options: Res<ReplayOptions>, config: Res<LevelConfig>) {
+    let Some(path) = options.record.clone() else {
+        return;
+    };
+    commands.insert_resource(Recorder {
+        path,
+        recording: Recording {
+            seed: config.seed,
+            ..default()
+        },
+    });
+}
+
+// load the recording to play back, and generate the level from the same seed.
+fn start_playback(
+    mut commands: Commands,
+    options: Res<ReplayOptions>,
+    mut config: ResMut<LevelConfig>,
+    mut clock: ResMut<Clock>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let Some(path) = &options.replay else {
+        return;
+    };
+
+    let recording = fs::read_to_string(path)
+        .map_err(|err| err.to_string())
+        .and_then(|text| Recording::parse(&text));
+    let recording = match recording {
+        Ok(recording) => recording,
+        Err(err) => {
+            error!("couldn't play back {}: {err}", path.display());
+            if options.headless {
+                exit.send(AppExit::error());
+            }
+            return;
+        }
+    };
+
+    config.seed = recording.seed;
+    clock.playback = true;
+    commands.insert_resource(Playback {
+        commands: recording.commands.into(),
+        end: recording.end,
+    });
+}
+
+// run ticks up to the next command in the recording and carry it out. commands are sent
+// before the frame's systems run, so they land between the same two ticks they did when
+// they were recorded. in a window, this goes at the clock's speed so it can be watched.
+pub fn play_back(world: &mut World) {
+    let headless = world.resource::<ReplayOptions>().headless;
+    let mut budget = {
+        let clock = world.resource::<Clock>();
+        match (headless, clock.paused) {
+            (true, _) => usize::MAX,
+            (false, true) => 0,
+            (false, false) => clock.speed,
+        }
+    };
+
+    loop {
+        let elapsed = world.resource::<Clock>().elapsed;
+        let mut playback = world.resource_mut::<Playback>();
+        let due = playback
+            .commands
+            .iter()
+            .take_while(|(tick, _)| *tick <= elapsed)
+            .count();
+        if due > 0 {
+            let actions: Vec<_> = playback
+                .commands
+                .drain(..due)
+                .map(|(_, action)| action)
+                .collect();
+            world.send_event_batch(actions);
+            return;
+        }
+
+        if playback.commands.is_empty() && elapsed >= playback.end {
+            // hand the game back to the player, or quit if there isn't one.
+            info!("replay finished at tick {elapsed}");
+            world.remove_resource::<Playback>();
+            world.resource_mut::<Clock>().playback = false;
+            if headless {
+                world.send_event(AppExit::Success);
+            }
+            return;
+        }
+
+        if budget == 0 {
+            return;
+        }
+        budget -= 1;
+        tick(world);
+    }
+}
+
+// note down every command, along with the tick it was carried out on.
+fn record_actions(
+    mut actions: EventReader<Action>,
+    mut ev_outcome: EventReader<Outcome>,
+    mut recorder: ResMut<Recorder>,
+    clock: Res<Clock>,
+) {
+    let mut changed = ev_outcome.read().count() > 0;
+    for action in actions.read() {
+        recorder.recording.commands.push((clock.elapsed, action.clone()));
+        changed = true;
+    }
+    if changed {
+        recorder.save(clock.elapsed);
+    }
+}
+
+fn save_on_exit(
+    mut ev_exit: EventReader<AppExit>,
+    mut recorder: ResMut<Recorder>,
+    clock: Res<Clock>,
+) {
+    if ev_exit.read().count() > 0 {
+        recorder.save(clock.elapsed);
+    }
+}
+
+// say how the level ended, for when nobody's watching.
+fn report_outcome(
+    mut ev_outcome: EventReader<Outcome>,
+    options: Res<ReplayOptions>,
+    clock: Res<Clock>,
+) {
+    for outcome in ev_outcome.read() {
+        if options.headless {
+            println!("{outcome:?} at tick {}", clock.elapsed);
+        }
+    }
+}
@@ -0,0 +1,317 @@
+use std::collections::VecDeque;
+
+use bevy::{prelude::*, utils::HashMap};
+use itertools::Itertools;
+use rand::Rng;
+
+use crate::{
+    wire::{curve_points, polyline_points},
+    TILE_SIZE,
+};
+
+// how many pieces a route is split into when checking it for crossings and clearance.
+const ROUTE_SAMPLES: usize = 16;
+
+// sideways offsets tried for each control point, in multiples of the distance between the ends.
+const CANDIDATE_OFFSETS: [f32; 7] = [-0.45, -0.3, -0.15, 0.0, 0.15, 0.3, 0.45];
+
+// how far wires have to keep from the nodes they don't connect to.
+const CLEARANCE: f32 = 4.0;
+
+// how many tiles beyond the outermost nodes orthogonal routes can wander.
+const ROUTING_MARGIN: i32 = 4;
+
+// how wires between nodes are shaped.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Routing {
+    // a gentle curve with random bends.
+    Random,
+    // the curve that crosses the fewest other wires without passing over any nodes. if there
+    // isn't one, the wire takes right angles along the tile grid instead.
+    Auto,
+}
+
+impl Routing {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "random" => Some(Routing::Random),
+            "auto" => Some(Routing::Auto),
+            _ => None,
+        }
+    }
+}
+
+// the shape of a wire, as it's stored on `Wire`.
+#[derive(Clone, Debug)]
+pub struct Route {
+    pub control_points: [Vec2; 2],
+    pub corners: Vec<Vec2>,
+}
+
+impl Route {
+    fn curve(control_points: [Vec2; 2]) -> Self {
+        Self {
+            control_points,
+            corners: vec![],
+        }
+    }
+
+    fn points(&self, ends: [Vec2; 2]) -> Vec<Vec2> {
+        if self.corners.is_empty() {
+            curve_points(ends, self.control_points, ROUTE_SAMPLES)
+        } else {
+            polyline_points(ends, &self.corners, ROUTE_SAMPLES)
+        }
+    }
+}
+
+// a node that wires are routed between and around.
+pub struct Obstacle {
+    pub tile: IVec2,
+    pub centre: Vec2,
+    pub radius: f32,
+}
+
+// shape a wire for each of `links`, which are pairs of indices into `obstacles`.
+pub fn route(
+    obstacles: &[Obstacle],
+    links: &[(usize, usize)],
+    routing: Routing,
+    rng: &mut impl Rng,
+) -> Vec<Route> {
+    match routing {
+        Routing::Random => links
+            .iter()
+            .map(|_| {
+                Route::curve([
+                    Vec2::new(0.2, rng.gen_range(-0.25..0.25)),
+                    Vec2::new(0.8, rng.gen_range(-0.25..0.25)),
+                ])
+            })
+            .collect(),
+        Routing::Auto => {
+            // wires are routed one at a time, each avoiding the ones routed before it.
+            let mut routed: Vec<((usize, usize), Vec<Vec2>)> = vec![];
+            let mut routes = vec![];
+            for &(a, b) in links {
+                let ends = [obstacles[a].centre, obstacles[b].centre];
+                let route = best_curve(obstacles, &routed, (a, b))
+                    .or_else(|| orthogonal(obstacles, (a, b)))
+                    .unwrap_or_else(|| Route::curve([Vec2::new(0.2, 0.0), Vec2::new(0.8, 0.0)]));
+                routed.push(((a, b), route.points(ends)));
+                routes.push(route);
+            }
+            routes
+        }
+    }
+}
+
+// the curve between `link`'s ends that keeps clear of other nodes and crosses the fewest
+// routed wires, preferring straighter curves when there's a tie.
+fn best_curve(
+    obstacles: &[Obstacle],
+    routed: &[((usize, usize), Vec<Vec2>)],
+    (a, b): (usize, usize),
+) -> Option<Route> {
+    let ends = [obstacles[a].centre, obstacles[b].centre];
+    CANDIDATE_OFFSETS
+        .iter()
+        .cartesian_product(CANDIDATE_OFFSETS.iter())
+        .map(|(&first, &second)| Route::curve([Vec2::new(0.25, first), Vec2::new(0.75, second)]))
+        .filter_map(|route| {
+            let points = route.points(ends);
+            if !clear(obstacles, (a, b), &points) {
+                return None;
+            }
+
+            // wires that share a node meet there anyway, so they don't count as crossing.
+            let crossed = routed
+                .iter()
+                .filter(|((c, d), _)| ![a, b].contains(c) && ![a, b].contains(d))
+                .map(|(_, other)| crossings(&points, other))
+                .sum::<usize>();
+            let bend = route.control_points.iter().map(|pt| pt.y.abs()).sum::<f32>();
+            Some((crossed, bend, route))
+        })
+        .min_by(|(c1, b1, _), (c2, b2, _)| c1.cmp(c2).then(b1.total_cmp(b2)))
+        .map(|(_, _, route)| route)
+}
+
+// whether every point keeps its distance from every node other than the two it runs between.
+fn clear(obstacles: &[Obstacle], (a, b): (usize, usize), points: &[Vec2]) -> bool {
+    obstacles
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != a && *i != b)
+        .all(|(_, obstacle)| {
+            points
+                .iter()
+                .all(|point| point.distance(obstacle.centre) >= obstacle.radius + CLEARANCE)
+        })
+}
+
+// how many times two lines made of straight pieces cross each other.
+fn crossings(first: &[Vec2], second: &[Vec2]) -> usize {
+    first
+        .iter()
+        .tuple_windows()
+        .cartesian_product(second.iter().tuple_windows().collect_vec())
+        .filter(|((p1, p2), (q1, q2))| segments_cross(**p1, **p2, **q1, **q2))
+        .count()
+}
+
+fn segments_cross(p1: Vec2, p2: Vec2, q1: Vec2, q2: Vec2) -> bool {
+    let side = |a: Vec2, b: Vec2, point: Vec2| (b - a).perp_dot(point - a);
+    side(q1, q2, p1) * side(q1, q2, p2) < 0.0 && side(p1, p2, q1) * side(p1, p2, q2) < 0.0
+}
+
+fn tile_centre(tile: IVec2) -> Vec2 {
+    tile.as_vec2() * TILE_SIZE - TILE_SIZE * 0.5
+}
+
+// route along the tile grid in straight lines and right angles, going around other nodes.
+// this is a breadth-first search, so the route found takes as few tiles as possible.
+fn orthogonal(obstacles: &[Obstacle], (a, b): (usize, usize)) -> Option<Route> {
+    let (start, goal) = (obstacles[a].tile, obstacles[b].tile);
+    let min = obstacles.iter().map(|o| o.tile).fold(start, IVec2::min) - ROUTING_MARGIN;
+    let max = obstacles.iter().map(|o| o.tile).fold(start, IVec2::max) + ROUTING_MARGIN;
+    let blocked = |tile: IVec2| {
+        tile != start
+            && tile != goal
+            && !clear(obstacles, (a, b), &[tile_centre(tile)])
+    };
+
+    let mut came_from = HashMap::default();
+    came_from.insert(start, start);
+    let mut queue = VecDeque::from([start]);
+    while let Some(tile) = queue.pop_front() {
+        if tile == goal {
+            break;
+        }
+        for step in [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y] {
+            let next = tile + step;
+            if next.cmplt(min).any() || next.cmpgt(max).any() || blocked(next) {
+                continue;
+            }
+            if !came_from.contains_key(&next) {
+                came_from.insert(next, tile);
+                queue.push_back(next);
+            }
+        }
+    }
+
+    let mut tiles = vec![goal];
+    while tiles[tiles.len() - 1] != start {
+        tiles.push(*came_from.get(&tiles[tiles.len() - 1])?);
+    }
+    tiles.reverse();
+
+    // the ends sit on node centres rather than tile centres, so add an elbow at each end
+    // to keep every piece of the route horizontal or vertical.
+    let (from, to) = (obstacles[a].centre, obstacles[b].centre);
+    let mut path = vec![from];
+    let centres = tiles.iter().map(|&tile| tile_centre(tile)).collect_vec();
+    path.push(Vec2::new(centres[0].x, from.y));
+    path.extend(centres.iter().copied());
+    path.push(Vec2::new(centres[centres.len() - 1].x, to.y));
+    path.push(to);
+
+    // only keep the points where the route turns.
+    path.dedup();
+    let corners = path
+        .iter()
+        .tuple_windows()
+        .filter(|(before, point, after)| (**point - **before).perp_dot(**after - **point) != 0.0)
+        .map(|(_, point, _)| *point)
+        .collect();
+    Some(Route {
+        control_points: [Vec2::new(0.2, 0.0), Vec2::new(0.8, 0.0)],
+        corners,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    fn obstacles(tiles: &[IVec2]) -> Vec<Obstacle> {
+        tiles
+            .iter()
+            .map(|&tile| Obstacle {
+                tile,
+                centre: tile_centre(tile),
+                radius: TILE_SIZE * 0.5,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn segments_cross_only_when_they_pass_through_each_other() {
+        let (a, b) = (Vec2::new(-1.0, -1.0), Vec2::new(1.0, 1.0));
+        assert!(segments_cross(a, b, Vec2::new(-1.0, 1.0), Vec2::new(1.0, -1.0)));
+        // parallel.
+        assert!(!segments_cross(a, b, Vec2::new(0.0, -1.0), Vec2::new(2.0, 1.0)));
+        // would cross if they were longer.
+        assert!(!segments_cross(a, b, Vec2::new(2.0, 0.0), Vec2::new(3.0, -1.0)));
+        // sharing an end.
+        assert!(!segments_cross(a, b, b, Vec2::new(2.0, 0.0)));
+    }
+
+    #[test]
+    fn orthogonal_routes_go_around_nodes_in_right_angles() {
+        let obstacles = obstacles(&[IVec2::new(0, 0), IVec2::new(6, 0), IVec2::new(3, 0)]);
+        let route = orthogonal(&obstacles, (0, 1)).unwrap();
+        assert!(!route.corners.is_empty());
+
+        let ends = [obstacles[0].centre, obstacles[1].centre];
+        let path = [&[ends[0]], route.corners.as_slice(), &[ends[1]]].concat();
+        for (a, b) in path.iter().tuple_windows() {
+            assert!(a.x == b.x || a.y == b.y, "{a} to {b} isn't straight");
+        }
+        assert!(clear(&obstacles, (0, 1), &route.points(ends)));
+    }
+
+    #[test]
+    fn orthogonal_routes_fail_when_a_node_is_walled_in() {
+        let mut tiles = vec![IVec2::new(0, 0), IVec2::new(8, 0)];
+        tiles.extend([IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y]);
+        assert!(orthogonal(&obstacles(&tiles), (0, 1)).is_none());
+    }
+
+    #[test]
+    fn random_routing_shapes_every_link() {
+        let obstacles = obstacles(&[IVec2::new(0, 0), IVec2::new(4, 0), IVec2::new(0, 4)]);
+        let links = [(0, 1), (1, 2), (2, 0)];
+        let routes = route(&obstacles, &links, Routing::Random, &mut StdRng::seed_from_u64(0));
+        assert_eq!(routes.len(), links.len());
+        assert!(routes.iter().all(|route| route.corners.is_empty()));
+    }
+
+    #[test]
+    fn auto_routing_keeps_clear_of_nodes_in_the_way() {
+        let obstacles = obstacles(&[IVec2::new(0, 0), IVec2::new(6, 0), IVec2::new(3, 0)]);
+        let links = [(0, 1), (0, 2), (2, 1)];
+        let routes = route(&obstacles, &links, Routing::Auto, &mut StdRng::seed_from_u64(0));
+        for (&(a, b), route) in links.iter().zip(&routes) {
+            let points = route.points([obstacles[a].centre, obstacles[b].centre]);
+            assert!(clear(&obstacles, (a, b), &points));
+        }
+    }
+
+    #[test]
+    fn crossings_counts_every_time_lines_cross() {
+        let line = [Vec2::new(0.0, 0.0), Vec2::new(10.0, 0.0)];
+        let zigzag = [
+            Vec2::new(1.0, 1.0),
+            Vec2::new(3.0, -1.0),
+            Vec2::new(5.0, 1.0),
+            Vec2::new(7.0, -1.0),
+            Vec2::new(7.0, -5.0),
+        ];
+        assert_eq!(crossings(&line, &zigzag), 3);
+        assert_eq!(crossings(&zigzag, &line), 3);
+        assert_eq!(crossings(&line, &zigzag[3..]), 0);
+    }
+}
@@ -1,11 +1,18 @@
 
 use bevy::{
-    asset::Handle, prelude::{Component, Entity, Query, Res}, reflect::Reflect, render::texture::Image
+    asset::{Assets, Handle}, color::Color, prelude::{Component, DetectChanges, Entity, Mut, Query, Res}, reflect::Reflect, render::texture::Image,
+    sprite::{Sprite, TextureAtlas, TextureAtlasLayout}, time::{Time, Timer, TimerMode}
 };
 
-use crate::assetloader::{HandleMap, ImageKey};
+use crate::assetloader::{AtlasKey, HandleMap, ImageKey};
 
-#[derive(Copy, Clone, Eq, PartialEq, Hash, Reflect)]
+// how long each frame of a server's animation is shown for.
+const FRAME_SECONDS: f32 = 0.4;
+
+// switches and coolers without power are dimmed.
+const POWERED_OFF_COLOR: Color = Color::srgb(0.4, 0.4, 0.4);
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Reflect)]
 pub enum ServerState {
     Healthy,
     Hacked,
@@ -14,6 +21,7 @@ pub enum ServerState {
     Off
 }
 
+#[derive(Copy, Clone, PartialEq)]
 pub enum ServerProgram {
     Heat,
     Reboot(usize),
@@ -28,21 +36,215 @@ pub struct Server {
     pub program: ServerProgram,
     pub temp: f32,
     pub overheat: f32,
+    // how hard the server is to hack. hacks take time proportional to this.
+    pub defence: f32,
+    // how much power the server draws from its generator while it's on.
+    pub power: f32,
+    // the generator or battery currently powering the server, if any.
+    pub supply: Option<Entity>,
     pub name: String,
 }
 
+impl Server {
+    // whether the virus can run programs on this server.
+    pub fn controlled(&self) -> bool {
+        matches!(self.state, ServerState::Hacked | ServerState::Infected)
+    }
+
+    // take the server off for `ticks` ticks, after which it comes back healthy.
+    // returns whether the virus was running on it, and so has been killed.
+    pub fn reboot(&mut self, ticks: usize) -> bool {
+        let killed = self.state == ServerState::Infected;
+        self.state = ServerState::Off;
+        self.program = ServerProgram::Reboot(ticks);
+        killed
+    }
+}
+
+// find the server with the given name.
+pub fn find_server<'a>(
+    servers: impl IntoIterator<Item = (Entity, &'a Server)>,
+    name: &str,
+) -> Option<Entity> {
+    servers
+        .into_iter()
+        .find(|(_, server)| server.name == name)
+        .map(|(entity, _)| entity)
+}
+
+// the name of the server `entity`, for telling the player about it.
+pub fn name_of<'a>(servers: &'a Query<&Server>, entity: Entity) -> &'a str {
+    servers.get(entity).map_or("a server", |server| server.name.as_str())
+}
+
 #[derive(Component)]
 pub struct Generator {
     pub load: f32,
     pub overload: f32,
     pub heat: f32,
-    pub overheat: f32
+    pub overheat: f32,
+    // generators shut down when they overheat, and start back up once they've cooled off.
+    pub online: bool,
+}
+
+// a ups. it charges while it's wired to an online generator, and keeps the servers wired
+// to it on for a while after their generator goes.
+#[derive(Component)]
+pub struct Battery {
+    // how much power the battery has stored, in units of power for a tick.
+    pub charge: f32,
+    pub capacity: f32,
+    // the generator the battery is charging from, if any.
+    pub grid: Option<Entity>,
+}
+
+impl Battery {
+    // whether the battery can power anything right now.
+    pub fn live(&self) -> bool {
+        self.grid.is_some() || self.charge > 0.0
+    }
+}
+
+// relays communication between whatever it's wired to, as long as it has power.
+// switches can't be hacked or infected.
+#[derive(Component)]
+pub struct Switch {
+    pub name: String,
+    // how much power the switch draws from its generator while it's on.
+    pub power: f32,
+    // the generator currently powering the switch, if any.
+    pub supply: Option<Entity>,
+}
+
+impl Switch {
+    pub fn on(&self) -> bool {
+        self.supply.is_some()
+    }
+}
+
+// whether communication passes through `entity`, because it's a switch that's on.
+pub fn relay(switches: &Query<&Switch>, entity: Entity) -> bool {
+    switches.get(entity).is_ok_and(Switch::on)
 }
 
+// find the switch with the given name.
+pub fn find_switch<'a>(
+    switches: impl IntoIterator<Item = (Entity, &'a Switch)>,
+    name: &str,
+) -> Option<Entity> {
+    switches
+        .into_iter()
+        .find(|(_, switch)| switch.name == name)
+        .map(|(entity, _)| entity)
+}
+
+// a crac unit. while it has power it cools every server within `radius` tiles of it.
+#[derive(Component)]
+pub struct Cooler {
+    pub name: String,
+    // how much power the cooler draws from its generator while it's on.
+    pub power: f32,
+    // temperature each server in range loses each tick.
+    pub cooling: f32,
+    pub radius: f32,
+    // the generator currently powering the cooler, if any.
+    pub supply: Option<Entity>,
+}
+
+impl Cooler {
+    pub fn on(&self) -> bool {
+        self.supply.is_some()
+    }
+}
 
+// find the cooler with the given name.
+pub fn find_cooler<'a>(
+    coolers: impl IntoIterator<Item = (Entity, &'a Cooler)>,
+    name: &str,
+) -> Option<Entity> {
+    coolers
+        .into_iter()
+        .find(|(_, cooler)| cooler.name == name)
+        .map(|(entity, _)| entity)
+}
 
-pub fn update_server_visuals(handle_map: Res<HandleMap<ImageKey>>, mut servers: Query<(&mut Handle<Image>, &Server)>,) {
-    for (mut texture, server) in &mut servers {
-        *texture = handle_map[&ImageKey::Server(server.state)].clone();
+// animation state of a server's sprite.
+#[derive(Component)]
+pub struct ServerSprite {
+    // the state the sprite sheet currently being shown is for.
+    pub shown: Option<ServerState>,
+    pub timer: Timer,
+}
+
+impl Default for ServerSprite {
+    fn default() -> Self {
+        Self {
+            shown: None,
+            timer: Timer::from_seconds(FRAME_SECONDS, TimerMode::Repeating),
+        }
     }
-}
\ No newline at end of file
+}
+
+// swap in the sprite sheet for each server's state, and step through its frames.
+// states without a sheet show the missing image instead.
+pub fn update_server_visuals(
+    time: Res<Time>,
+    images: Res<HandleMap<ImageKey>>,
+    atlases: Res<HandleMap<AtlasKey>>,
+    layouts: Res<Assets<TextureAtlasLayout>>,
+    mut servers: Query<(&Server, &mut ServerSprite, &mut Handle<Image>, &mut TextureAtlas)>,
+) {
+    for (server, mut sprite, mut texture, mut atlas) in &mut servers {
+        // images get swapped out when the asset manifest is edited.
+        if images.is_changed() {
+            sprite.shown = None;
+        }
+
+        if sprite.shown != Some(server.state) {
+            sprite.shown = Some(server.state);
+            atlas.index = 0;
+            match (
+                images.get(&ImageKey::Server(server.state)),
+                atlases.get(&AtlasKey::Server(server.state)),
+            ) {
+                (Some(image), Some(layout)) => {
+                    *texture = image.clone();
+                    atlas.layout = layout.clone();
+                }
+                _ => {
+                    *texture = images.get_or_missing(&ImageKey::Missing);
+                    atlas.layout = atlases[&AtlasKey::Single].clone();
+                }
+            }
+        }
+
+        if sprite.timer.tick(time.delta()).just_finished() {
+            let frames = layouts
+                .get(&atlas.layout)
+                .map_or(1, |layout| layout.textures.len());
+            atlas.index = (atlas.index + 1) % frames;
+        }
+    }
+}
+
+// dim switches that have lost power.
+pub fn update_switch_visuals(mut switches: Query<(&Switch, &mut Sprite)>) {
+    for (switch, sprite) in &mut switches {
+        dim_unpowered(sprite, switch.on());
+    }
+}
+
+// dim coolers that have lost power.
+pub fn update_cooler_visuals(mut coolers: Query<(&Cooler, &mut Sprite)>) {
+    for (cooler, sprite) in &mut coolers {
+        dim_unpowered(sprite, cooler.on());
+    }
+}
+
+// only touch the sprite when its color changes, so it isn't marked as changed every frame.
+fn dim_unpowered(mut sprite: Mut<Sprite>, on: bool) {
+    let color = if on { Color::WHITE } else { POWERED_OFF_COLOR };
+    if sprite.color != color {
+        sprite.color = color;
+    }
+}
@@ -0,0 +1,625 @@
+use std::collections::VecDeque;
+
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+use crate::{
+    firewall::Firewall,
+    hack::HackProgress,
+    level::{Blueprint, LevelConfig, LevelMode, NodeKind},
+    notices::Notice,
+    server::{Battery, Cooler, Generator, Server, ServerProgram, ServerState, Switch},
+    text_input::{Action, Rejected},
+    virus::Virus,
+    wire::Wire,
+    TILE_SIZE,
+};
+
+// most steps the solver looks ahead before it gives up on finding a win.
+pub const MAX_STEPS: usize = 64;
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(Update, give_hints);
+}
+
+// a level boiled down to the moves, hacks and cuts that decide whether it can be won.
+//
+// it's a simplified version of the rules. hacks are done as soon as they're started and the
+// defender never gets a look in. everything else that takes time is left until the virus
+// waits: then every server that's overheating, because it's running the heat program or every
+// cooler around it has been cut, breaks, and every server that's off and has a way back, from
+// a reboot or a power wire, comes back on. a solution here is a good plan for the real thing,
+// but not a promise.
+pub struct Puzzle {
+    nodes: Vec<PuzzleNode>,
+    // communication links between servers and switches. power links don't matter here.
+    links: Vec<PuzzleLink>,
+    start: Position,
+    // the instance of the virus on each node to begin with, if any.
+    instances: Vec<Option<String>>,
+}
+
+struct PuzzleNode {
+    name: String,
+    kind: NodeKind,
+    // whether communication passes through the node, because it's a switch that's on.
+    relay: bool,
+    // the coolers keeping the node cool, or in reach of it for cutting.
+    coolers: Vec<usize>,
+    // whether the node overheats even with its coolers on, because it's running the heat program.
+    heating: bool,
+    // whether the node comes back on after it's been turned off.
+    recovers: bool,
+}
+
+struct PuzzleLink {
+    ends: [usize; 2],
+    // the node hosting the firewall on the link, if it has one that's up.
+    firewall: Option<usize>,
+}
+
+// everything that changes as a puzzle is played.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct Position {
+    // the state of every server, by node. `None` for nodes that aren't servers.
+    states: Vec<Option<ServerState>>,
+    // whether each cooler has had its power cut, by node.
+    cut: Vec<bool>,
+}
+
+// something the virus can do, in terms of a puzzle's nodes.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Step {
+    Hack { source: usize, target: usize },
+    Move { from: usize, to: usize },
+    Cut { server: usize, cooler: usize },
+    Wait,
+}
+
+impl Puzzle {
+    // the puzzle a blueprint makes, once its nodes have been placed on `tiles`.
+    pub fn from_blueprint(blueprint: &Blueprint, tiles: &[IVec2]) -> Puzzle {
+        let start = blueprint.start_server();
+        let nodes = blueprint
+            .nodes
+            .iter()
+            .zip(blueprint.names())
+            .enumerate()
+            .map(|(node, (&kind, name))| PuzzleNode {
+                name,
+                kind,
+                relay: kind == NodeKind::Switch,
+                coolers: match kind {
+                    NodeKind::Server => blueprint.coolers_near(tiles, node),
+                    _ => vec![],
+                },
+                heating: false,
+                // every server is wired to power to begin with.
+                recovers: true,
+            })
+            .collect::<Vec<_>>();
+
+        let links = blueprint
+            .links
+            .iter()
+            .enumerate()
+            .filter(|(_, &(a, b))| communicates(nodes[a].kind) && communicates(nodes[b].kind))
+            .map(|(i, &(a, b))| PuzzleLink {
+                ends: [a, b],
+                firewall: blueprint.firewalls.get(&i).copied(),
+            })
+            .collect();
+
+        let states = (0..nodes.len())
+            .map(|node| match nodes[node].kind {
+                NodeKind::Server if Some(node) == start => Some(ServerState::Infected),
+                NodeKind::Server if blueprint.hacked.contains(&node) => Some(ServerState::Hacked),
+                NodeKind::Server => Some(ServerState::Healthy),
+                _ => None,
+            })
+            .collect();
+        let instances = (0..nodes.len())
+            .map(|node| (Some(node) == start).then(|| "v1".to_string()))
+            .collect();
+
+        Puzzle {
+            start: Position {
+                states,
+                cut: vec![false; nodes.len()],
+            },
+            nodes,
+            links,
+            instances,
+        }
+    }
+
+    // the puzzle the network is in right now. hacks that are already running count as done, and
+    // servers that are off come back if they're rebooting or still wired to a generator or a
+    // battery.
+    pub fn from_world(
+        servers: &Query<(Entity, &Server, &Transform, Option<&Virus>)>,
+        switches: &Query<(Entity, &Switch)>,
+        coolers: &Query<(Entity, &Cooler, &Transform)>,
+        wires: &Query<(&Wire, Option<&Firewall>)>,
+        hacks: &Query<&HackProgress>,
+        power: &Query<Entity, Or<(With<Generator>, With<Battery>)>>,
+    ) -> Puzzle {
+        let mut nodes = vec![];
+        let mut states = vec![];
+        let mut cut = vec![];
+        let mut instances = vec![];
+        let mut indices = HashMap::new();
+
+        let hacking: HashSet<_> = hacks.iter().map(|hack| hack.target).collect();
+        for (entity, server, transform, virus) in servers {
+            indices.insert(entity, nodes.len());
+            let centre = transform.translation.truncate();
+            let near: Vec<_> = coolers
+                .iter()
+                .filter(|(_, cooler, cooler_transform)| {
+                    let distance = centre.distance(cooler_transform.translation.truncate());
+                    distance <= cooler.radius * TILE_SIZE
+                })
+                .map(|(cooler, _, _)| cooler)
+                .collect();
+            let powered = wires.iter().any(|(wire, _)| {
+                wire.cuts.is_empty()
+                    && power.iter().any(|supply| wire.connects(entity, supply))
+            });
+            let recovers = matches!(server.program, ServerProgram::Reboot(_)) || powered;
+            let heating = server.program == ServerProgram::Heat;
+            nodes.push((server.name.clone(), NodeKind::Server, false, near, heating, recovers));
+            let state = match server.state {
+                ServerState::Healthy if hacking.contains(&entity) => ServerState::Hacked,
+                state => state,
+            };
+            states.push(Some(state));
+            cut.push(false);
+            instances.push(virus.map(|virus| virus.name.clone()));
+        }
+        for (entity, switch) in switches {
+            indices.insert(entity, nodes.len());
+            nodes.push((switch.name.clone(), NodeKind::Switch, switch.on(), vec![], false, false));
+            states.push(None);
+            cut.push(false);
+            instances.push(None);
+        }
+        for (entity, cooler, _) in coolers {
+            indices.insert(entity, nodes.len());
+            nodes.push((cooler.name.clone(), NodeKind::Cooler, false, vec![], false, false));
+            states.push(None);
+            cut.push(!cooler.on());
+            instances.push(None);
+        }
+
+        let nodes = nodes
+            .into_iter()
+            .map(|(name, kind, relay, near, heating, recovers)| PuzzleNode {
+                name,
+                kind,
+                relay,
+                coolers: near.iter().map(|cooler| indices[cooler]).collect(),
+                heating,
+                recovers,
+            })
+            .collect::<Vec<_>>();
+
+        let links = wires
+            .iter()
+            .filter(|(wire, _)| wire.cuts.is_empty())
+            .filter_map(|(wire, firewall)| {
+                let [a, b] = wire.terminals;
+                let ends = [*indices.get(&a)?, *indices.get(&b)?];
+                if !ends.iter().all(|&node| communicates(nodes[node].kind)) {
+                    return None;
+                }
+                Some(PuzzleLink {
+                    ends,
+                    firewall: firewall
+                        .filter(|firewall| firewall.up)
+                        .and_then(|firewall| indices.get(&firewall.host).copied()),
+                })
+            })
+            .collect();
+
+        Puzzle {
+            nodes,
+            links,
+            start: Position { states, cut },
+            instances,
+        }
+    }
+
+    // the steps to win the puzzle, or `None` if it can't be won within `max_steps` of them.
+    //
+    // hacking a server never gets in the way of anything else, so whenever there's a hack to
+    // be done it's done first and nothing else is tried. that keeps the search small, at the
+    // cost of solutions sometimes hacking servers that didn't need it.
+    pub fn solve(&self, max_steps: usize) -> Option<Vec<Step>> {
+        let mut visited: HashSet<Position> = [self.start.clone()].into_iter().collect();
+        let mut queue = VecDeque::from([(self.start.clone(), vec![])]);
+        while let Some((position, steps)) = queue.pop_front() {
+            if self.won(&position) {
+                return Some(steps);
+            }
+            if steps.len() >= max_steps {
+                continue;
+            }
+
+            for step in self.steps(&position) {
+                let next = self.play(&position, step);
+                if self.lost(&next) || !visited.insert(next.clone()) {
+                    continue;
+                }
+                let mut steps = steps.clone();
+                steps.push(step);
+                queue.push_back((next, steps));
+            }
+        }
+        None
+    }
+
+    // the commands that carry out `steps`, in order.
+    pub fn actions(&self, steps: &[Step]) -> Vec<Action> {
+        let mut instances = self.instances.clone();
+        let name = |node: usize| self.nodes[node].name.clone();
+        steps
+            .iter()
+            .map(|&step| match step {
+                Step::Hack { source, target } => Action::Hack {
+                    source: name(source),
+                    target: name(target),
+                },
+                Step::Move { from, to } => {
+                    // instances only need naming while there's more than one of them.
+                    let several = instances.iter().flatten().count() > 1;
+                    let instance = instances[from].take();
+                    instances[to] = instance.clone();
+                    Action::Move {
+                        instance: instance.filter(|_| several),
+                        server: name(to),
+                    }
+                }
+                Step::Cut { server, cooler } => Action::Cut {
+                    server: name(server),
+                    target: name(cooler),
+                    power: false,
+                },
+                Step::Wait => Action::Wait,
+            })
+            .collect()
+    }
+
+    fn controlled(&self, position: &Position, node: usize) -> bool {
+        matches!(
+            position.states[node],
+            Some(ServerState::Hacked | ServerState::Infected)
+        )
+    }
+
+    // whether the server on `node` breaks once time passes.
+    fn overheating(&self, position: &Position, node: usize) -> bool {
+        let PuzzleNode {
+            coolers, heating, ..
+        } = &self.nodes[node];
+        let uncooled = !coolers.is_empty() && coolers.iter().all(|&cooler| position.cut[cooler]);
+        let running = matches!(
+            position.states[node],
+            Some(ServerState::Healthy | ServerState::Hacked | ServerState::Infected)
+        );
+        running && (*heating || uncooled)
+    }
+
+    // whether the server on `node` comes back on once time passes.
+    fn recovering(&self, position: &Position, node: usize) -> bool {
+        position.states[node] == Some(ServerState::Off) && self.nodes[node].recovers
+    }
+
+    // every server that still works is under the virus' control.
+    fn won(&self, position: &Position) -> bool {
+        position.states.iter().flatten().all(|state| {
+            matches!(
+                state,
+                ServerState::Hacked | ServerState::Infected | ServerState::Broken
+            )
+        }) && !self.lost(position)
+    }
+
+    // the virus isn't running anywhere any more.
+    fn lost(&self, position: &Position) -> bool {
+        !position.states.contains(&Some(ServerState::Infected))
+    }
+
+    // whether the firewall on a link, if it has one, stops anything crossing it.
+    fn blocked(&self, position: &Position, link: &PuzzleLink) -> bool {
+        link.firewall.is_some_and(|host| {
+            !matches!(
+                position.states[host],
+                Some(ServerState::Off | ServerState::Broken)
+            )
+        })
+    }
+
+    // the nodes `source` can reach over open links, through switches that relay.
+    fn reachable(&self, position: &Position, source: usize) -> Vec<usize> {
+        let mut visited: HashSet<usize> = [source].into_iter().collect();
+        let mut frontier = vec![source];
+        let mut reached = vec![];
+        while let Some(node) = frontier.pop() {
+            for link in &self.links {
+                if self.blocked(position, link) {
+                    continue;
+                }
+                let other = match link.ends {
+                    [a, b] if a == node => b,
+                    [a, b] if b == node => a,
+                    _ => continue,
+                };
+                if !visited.insert(other) {
+                    continue;
+                }
+                reached.push(other);
+                if self.nodes[other].relay {
+                    frontier.push(other);
+                }
+            }
+        }
+        reached
+    }
+
+    // everything worth trying from `position`.
+    fn steps(&self, position: &Position) -> Vec<Step> {
+        let servers = || (0..self.nodes.len()).filter(|&node| position.states[node].is_some());
+
+        let hack = servers()
+            .filter(|&source| self.controlled(position, source))
+            .find_map(|source| {
+                self.reachable(position, source)
+                    .into_iter()
+                    .find(|&target| position.states[target] == Some(ServerState::Healthy))
+                    .map(|target| Step::Hack { source, target })
+            });
+        if let Some(hack) = hack {
+            return vec![hack];
+        }
+
+        let mut steps = vec![];
+        for from in servers().filter(|&n| position.states[n] == Some(ServerState::Infected)) {
+            steps.extend(
+                self.reachable(position, from)
+                    .into_iter()
+                    .filter(|&to| position.states[to] == Some(ServerState::Hacked))
+                    .map(|to| Step::Move { from, to }),
+            );
+        }
+        // cutting a communication link only ever takes routes away, so only coolers get cut.
+        // it doesn't matter which server does it, so the first one in reach is used.
+        let coolers = (0..self.nodes.len())
+            .filter(|&node| self.nodes[node].kind == NodeKind::Cooler && !position.cut[node]);
+        for cooler in coolers {
+            let server = servers().find(|&server| {
+                self.controlled(position, server) && self.nodes[server].coolers.contains(&cooler)
+            });
+            if let Some(server) = server {
+                steps.push(Step::Cut { server, cooler });
+            }
+        }
+        if servers().any(|node| self.overheating(position, node) || self.recovering(position, node))
+        {
+            steps.push(Step::Wait);
+        }
+        steps
+    }
+
+    // the position after `step` is taken from `position`.
+    fn play(&self, position: &Position, step: Step) -> Position {
+        let mut next = position.clone();
+        match step {
+            Step::Hack { target, .. } => {
+                next.states[target] = Some(ServerState::Hacked);
+            }
+            Step::Move { from, to } => {
+                next.states[from] = Some(ServerState::Hacked);
+                next.states[to] = Some(ServerState::Infected);
+            }
+            Step::Cut { cooler, .. } => {
+                next.cut[cooler] = true;
+            }
+            // servers that come back on start out cool, so none of them break on the same wait.
+            Step::Wait => {
+                for node in 0..self.nodes.len() {
+                    if self.overheating(position, node) {
+                        next.states[node] = Some(ServerState::Broken);
+                    } else if self.recovering(position, node) {
+                        next.states[node] = Some(ServerState::Healthy);
+                    }
+                }
+            }
+        }
+        next
+    }
+}
+
+// whether a node passes communication on at all. everything else is only wired for power.
+fn communicates(kind: NodeKind) -> bool {
+    matches!(kind, NodeKind::Server | NodeKind::Switch)
+}
+
+// run `hint` commands, suggesting the next step towards winning the level.
+fn give_hints(
+    mut actions: EventReader<Action>,
+    mut rejected: EventWriter<Rejected>,
+    mut notices: EventWriter<Notice>,
+    config: Res<LevelConfig>,
+    servers: Query<(Entity, &Server, &Transform, Option<&Virus>)>,
+    switches: Query<(Entity, &Switch)>,
+    coolers: Query<(Entity, &Cooler, &Transform)>,
+    wires: Query<(&Wire, Option<&Firewall>)>,
+    hacks: Query<&HackProgress>,
+    power: Query<Entity, Or<(With<Generator>, With<Battery>)>>,
+) {
+    for action in actions.read() {
+        let Action::Hint = action else {
+            continue;
+        };
+
+        if config.mode == LevelMode::Challenge {
+            rejected.send(Rejected("there are no hints in challenge mode".to_string()));
+            continue;
+        }
+
+        let puzzle = Puzzle::from_world(&servers, &switches, &coolers, &wires, &hacks, &power);
+        let Some(steps) = puzzle.solve(MAX_STEPS) else {
+            rejected.send(Rejected("there's no way to win from here".to_string()));
+            continue;
+        };
+        let hint = match steps.first() {
+            Some(&step) => format!("hint: try `{}`", puzzle.actions(&[step])[0]),
+            None => "hint: there's nothing left to do but wait for your hacks".to_string(),
+        };
+        notices.send(Notice(hint));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // servers wired one after the other, with the virus on the first.
+    fn chain(servers: usize) -> Blueprint {
+        let mut blueprint = Blueprint::default();
+        for server in 0..servers {
+            blueprint.add(NodeKind::Server);
+            if server > 0 {
+                blueprint.link(server - 1, server);
+            }
+        }
+        blueprint
+    }
+
+    // every node on its own row, far enough apart to be out of reach of each other's coolers.
+    fn spread_out(blueprint: &Blueprint) -> Vec<IVec2> {
+        (0..blueprint.nodes.len() as i32).map(|n| IVec2::new(0, n * 30)).collect()
+    }
+
+    #[test]
+    fn solves_a_chain_by_hacking_down_it() {
+        let blueprint = chain(3);
+        let puzzle = Puzzle::from_blueprint(&blueprint, &spread_out(&blueprint));
+        let steps = puzzle.solve(MAX_STEPS).unwrap();
+        assert_eq!(
+            steps,
+            [
+                Step::Hack {
+                    source: 0,
+                    target: 1
+                },
+                Step::Hack {
+                    source: 1,
+                    target: 2
+                },
+            ]
+        );
+        assert!(puzzle.actions(&steps)[0].to_string().ends_with("hack b"));
+    }
+
+    #[test]
+    fn gives_up_on_puzzles_longer_than_max_steps() {
+        let blueprint = chain(MAX_STEPS + 2);
+        let puzzle = Puzzle::from_blueprint(&blueprint, &spread_out(&blueprint));
+        assert_eq!(puzzle.solve(MAX_STEPS), None);
+        assert_eq!(puzzle.solve(MAX_STEPS + 1).map(|steps| steps.len()), Some(MAX_STEPS + 1));
+    }
+
+    #[test]
+    fn firewalls_block_the_way_while_their_host_is_up() {
+        let mut blueprint = chain(2);
+        blueprint.firewalls.insert(0, 1);
+        let puzzle = Puzzle::from_blueprint(&blueprint, &spread_out(&blueprint));
+        assert_eq!(puzzle.solve(MAX_STEPS), None);
+    }
+
+    #[test]
+    fn firewalls_come_down_when_their_host_overheats() {
+        // a is wired to b behind a firewall hosted by c, which is kept cool by d. cutting d
+        // breaks c once time passes, taking the firewall down with it.
+        let mut blueprint = chain(2);
+        let host = blueprint.add(NodeKind::Server);
+        let cooler = blueprint.add(NodeKind::Cooler);
+        blueprint.link(0, host);
+        blueprint.firewalls.insert(0, host);
+        let mut tiles = spread_out(&blueprint);
+        tiles[cooler] = tiles[host] + IVec2::X;
+
+        let puzzle = Puzzle::from_blueprint(&blueprint, &tiles);
+        let steps = puzzle.solve(MAX_STEPS).unwrap();
+        assert_eq!(
+            steps,
+            [
+                Step::Hack {
+                    source: 0,
+                    target: host
+                },
+                Step::Cut {
+                    server: host,
+                    cooler
+                },
+                Step::Wait,
+                Step::Hack {
+                    source: 0,
+                    target: 1
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn servers_only_break_once_time_passes_after_their_cooler_is_cut() {
+        // a and b share a cooler, c. cutting it leaves them both running until the virus waits.
+        let mut blueprint = chain(2);
+        let cooler = blueprint.add(NodeKind::Cooler);
+        let mut tiles = spread_out(&blueprint);
+        tiles[cooler] = tiles[0] + IVec2::X;
+        tiles[1] = tiles[0] + IVec2::Y * 4;
+        let puzzle = Puzzle::from_blueprint(&blueprint, &tiles);
+
+        let hacked = puzzle.play(
+            &puzzle.start,
+            Step::Hack {
+                source: 0,
+                target: 1,
+            },
+        );
+        let cut = puzzle.play(&hacked, Step::Cut { server: 0, cooler });
+        assert_eq!(cut.states, hacked.states);
+        assert!(puzzle.steps(&cut).contains(&Step::Wait));
+
+        let waited = puzzle.play(&cut, Step::Wait);
+        assert_eq!(waited.states[0], Some(ServerState::Broken));
+        assert_eq!(waited.states[1], Some(ServerState::Broken));
+        assert!(puzzle.lost(&waited));
+    }
+
+    #[test]
+    fn servers_that_are_off_come_back_once_time_passes() {
+        let blueprint = chain(2);
+        let mut puzzle = Puzzle::from_blueprint(&blueprint, &spread_out(&blueprint));
+        puzzle.start.states[1] = Some(ServerState::Off);
+        assert_eq!(
+            puzzle.solve(MAX_STEPS).unwrap(),
+            [
+                Step::Wait,
+                Step::Hack {
+                    source: 0,
+                    target: 1
+                },
+            ]
+        );
+
+        // without a way back, the server never can be hacked.
+        puzzle.nodes[1].recovers = false;
+        assert_eq!(puzzle.solve(MAX_STEPS), None);
+    }
+}
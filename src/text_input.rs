@@ -1,17 +1,21 @@
+use std::fmt;
+
 use bevy::prelude::*;
 use bevy_simple_text_input::{TextInputBundle, TextInputInactive, TextInputSubmitEvent};
 use itertools::Itertools;
 
-use crate::{
-    rendering::InGameCamera,
-    server::{Server, ServerState},
-};
+use crate::{clock::ClockMode, rendering::InGameCamera, upgrades::Upgrade};
 
 const BORDER_COLOR_ACTIVE: Color = Color::srgb(0.75, 0.52, 0.99);
 const BORDER_COLOR_INACTIVE: Color = Color::srgb(0.25, 0.25, 0.25);
 const TEXT_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
 const BACKGROUND_COLOR: Color = Color::srgb(0.15, 0.15, 0.15);
 
+// the screen-sized node behind the text box. it's always under the cursor, so it doesn't
+// count as the cursor being over the ui.
+#[derive(Component)]
+pub struct Backdrop;
+
 // i'm using a text box as input for doing all the actions,
 // which isn't ideal, we should be using some kind of UI
 pub fn setup_textbox(mut commands: Commands) {
@@ -30,6 +34,7 @@ pub fn setup_textbox(mut commands: Commands) {
             // Make this container node bundle to be Interactive so that clicking on it removes
             // focus from the text input.
             Interaction::None,
+            Backdrop,
         ))
         .with_children(|parent| {
             parent.spawn((
@@ -99,69 +104,177 @@ pub fn focus(
         camera.single_mut().translation += Vec3::new(intent.x, intent.y, 0.0) * 2.;
     }
 }
-/* 
-fn validate_move(
-    connections: &mut Connections,
-    servers: &mut Query<(Entity, &mut Server)>,
-    server_name: &str,
-) -> bool {
-    let (infected_entity, _) = servers
-        .iter()
-        .filter(|(_, server)| server.state == ServerState::Infected)
-        .next()
-        .expect("one server should be infected");
 
-    for (_, server) in &connections.graph[&infected_entity] {
-        if let Some((_, server)) = servers
-            .iter_mut()
-            .filter(|(entity, _)| server == entity)
-            .next()
-        {
-            if server.name == server_name && server.state == ServerState::Hacked {
-                return true;
-            }
-        }
-    }
-    return false;
+// a parsed console command. systems that carry out commands read these as events.
+#[derive(Event, Clone, Debug)]
+pub enum Action {
+    // `instance` can be left out while the virus is only running in one place.
+    Move { instance: Option<String>, server: String },
+    Fork { instance: Option<String>, server: String },
+    Hack { source: String, target: String },
+    Reboot { server: String },
+    Cycle { server: String },
+    Heat { server: String },
+    // `power` cuts a switch's power rather than the wire to it.
+    Cut {
+        server: String,
+        target: String,
+        power: bool,
+    },
+    Upgrade { upgrade: Upgrade },
+    Wait,
+    Pause,
+    Speed { speed: usize },
+    Mode { mode: ClockMode },
+    Inspect { server: String },
+    Volume { percent: usize },
+    Heatmap,
+    Undo,
+    Redo,
+    Hint,
 }
 
-// parse command and do action based on it
+// a command that was either malformed or couldn't be carried out.
+#[derive(Event)]
+pub struct Rejected(pub String);
+
+// parse command and send it as an action for the relevant system to carry out.
 pub fn command(
     mut events: EventReader<TextInputSubmitEvent>,
-    mut connections: ResMut<Connections>,
-    mut servers: Query<(Entity, &mut Server)>,
+    mut actions: EventWriter<Action>,
+    mut rejected: EventWriter<Rejected>,
 ) {
     for event in events.read() {
-        match event.value.split_whitespace().collect_vec()[..] {
-            ["move", server_name] => {
-                if validate_move(&mut connections, &mut servers, server_name) {
-                    for (_, mut server) in &mut servers {
-                        if server.state == ServerState::Infected {
-                            server.state = ServerState::Hacked;
-                        }
-                    }
-                    for (_, mut server) in &mut servers {
-                        if server.name == server_name {
-                            server.state = ServerState::Infected;
-                        }
-                    }
-                }
+        match parse(&event.value) {
+            Ok(action) => {
+                actions.send(action);
             }
-            ["msg", server, action] => match action {
-                "reboot" => {}
-                "cycle" => {}
-                "heat" => {}
-                _ => println!("bad command"),
+            Err(reason) => {
+                rejected.send(Rejected(reason));
+            }
+        }
+    }
+}
+
+// turn a command into the action it asks for. `Action`'s `Display` goes the other way.
+pub fn parse(command: &str) -> Result<Action, String> {
+    let action = match command.split_whitespace().collect_vec()[..] {
+        ["wait"] => Action::Wait,
+        ["pause"] => Action::Pause,
+        ["speed", speed] => match speed.parse() {
+            Ok(speed) => Action::Speed { speed },
+            Err(_) => return Err(format!("{speed} isn't a speed")),
+        },
+        ["mode", mode] => match mode {
+            "realtime" => Action::Mode {
+                mode: ClockMode::RealTime,
+            },
+            "turns" => Action::Mode {
+                mode: ClockMode::TurnBased,
             },
-            ["msg", corrupted_server, "hack", healthy_server] => {}
-            ["upd", action] => match action {
-                "cycle" => {}
-                "heat" => {}
-                _ => println!("bad command"),
+            _ => return Err(format!("no clock mode called {mode}")),
+        },
+        ["inspect", server] => Action::Inspect {
+            server: server.to_string(),
+        },
+        ["heatmap"] => Action::Heatmap,
+        ["undo"] => Action::Undo,
+        ["redo"] => Action::Redo,
+        ["hint"] => Action::Hint,
+        ["volume", percent] => match percent.parse() {
+            Ok(percent) => Action::Volume { percent },
+            Err(_) => return Err(format!("{percent} isn't a volume")),
+        },
+        ["move", server] => Action::Move {
+            instance: None,
+            server: server.to_string(),
+        },
+        ["move", instance, server] => Action::Move {
+            instance: Some(instance.to_string()),
+            server: server.to_string(),
+        },
+        ["fork", server] => Action::Fork {
+            instance: None,
+            server: server.to_string(),
+        },
+        ["fork", instance, server] => Action::Fork {
+            instance: Some(instance.to_string()),
+            server: server.to_string(),
+        },
+        ["msg", server, action] => match action {
+            "reboot" => Action::Reboot {
+                server: server.to_string(),
             },
-            ["upd", "hack", server] => {}
-            _ => println!("bad command"),
+            "cycle" => Action::Cycle {
+                server: server.to_string(),
+            },
+            "heat" => Action::Heat {
+                server: server.to_string(),
+            },
+            _ => return Err("bad command".to_string()),
+        },
+        ["msg", server, "cut", target] => Action::Cut {
+            server: server.to_string(),
+            target: target.to_string(),
+            power: false,
+        },
+        ["msg", server, "cut", target, "power"] => Action::Cut {
+            server: server.to_string(),
+            target: target.to_string(),
+            power: true,
+        },
+        ["msg", source, "hack", target] => Action::Hack {
+            source: source.to_string(),
+            target: target.to_string(),
+        },
+        ["upd", upgrade] => match Upgrade::from_name(upgrade) {
+            Some(upgrade) => Action::Upgrade { upgrade },
+            None => return Err(format!("no upgrade called {upgrade}")),
+        },
+        _ => return Err("bad command".to_string()),
+    };
+    Ok(action)
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Action::Move {
+                instance: Some(instance),
+                server,
+            } => write!(f, "move {instance} {server}"),
+            Action::Move { server, .. } => write!(f, "move {server}"),
+            Action::Fork {
+                instance: Some(instance),
+                server,
+            } => write!(f, "fork {instance} {server}"),
+            Action::Fork { server, .. } => write!(f, "fork {server}"),
+            Action::Hack { source, target } => write!(f, "msg {source} hack {target}"),
+            Action::Reboot { server } => write!(f, "msg {server} reboot"),
+            Action::Cycle { server } => write!(f, "msg {server} cycle"),
+            Action::Heat { server } => write!(f, "msg {server} heat"),
+            Action::Cut {
+                server,
+                target,
+                power: false,
+            } => write!(f, "msg {server} cut {target}"),
+            Action::Cut { server, target, .. } => write!(f, "msg {server} cut {target} power"),
+            Action::Upgrade { upgrade } => write!(f, "upd {}", upgrade.name()),
+            Action::Wait => write!(f, "wait"),
+            Action::Pause => write!(f, "pause"),
+            Action::Speed { speed } => write!(f, "speed {speed}"),
+            Action::Mode {
+                mode: ClockMode::RealTime,
+            } => write!(f, "mode realtime"),
+            Action::Mode {
+                mode: ClockMode::TurnBased,
+            } => write!(f, "mode turns"),
+            Action::Inspect { server } => write!(f, "inspect {server}"),
+            Action::Volume { percent } => write!(f, "volume {percent}"),
+            Action::Heatmap => write!(f, "heatmap"),
+            Action::Undo => write!(f, "undo"),
+            Action::Redo => write!(f, "redo"),
+            Action::Hint => write!(f, "hint"),
         }
     }
 }
-*/
\ No newline at end of file
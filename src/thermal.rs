@@ -0,0 +1,136 @@
+use bevy::prelude::*;
+
+use crate::{
+    clock::Tick,
+    economy::{cost, Compute},
+    notices::Notice,
+    server::{find_server, name_of, Cooler, Server, ServerProgram, ServerState},
+    text_input::{Action, Rejected},
+    TILE_SIZE,
+};
+
+// temperature a heating server gains each tick.
+const HEAT_RATE: f32 = 0.05;
+
+// temperature every server that's on gains each tick, just from running.
+const IDLE_HEAT: f32 = 0.01;
+
+// temperature every server loses each tick on its own. this keeps up with `IDLE_HEAT`, so a
+// server left to itself never overheats.
+const COOLING_RATE: f32 = 0.01;
+
+// temperature servers that are on gain each tick on top of `IDLE_HEAT` while they're in reach
+// of a cooler, powered or not. they're packed in tight around it, so they need it to keep up.
+const RACK_HEAT: f32 = 0.02;
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_event::<ServerOverheated>()
+        .add_systems(Update, (start_heating, report_overheating))
+        .add_systems(Tick, heat_servers);
+}
+
+#[derive(Event)]
+pub struct ServerOverheated {
+    pub server: Entity,
+    // whether the virus was running on the server, and so has been killed.
+    pub virus_killed: bool,
+}
+
+// run `msg <server> heat` commands.
+fn start_heating(
+    mut actions: EventReader<Action>,
+    mut rejected: EventWriter<Rejected>,
+    mut servers: Query<(Entity, &mut Server)>,
+    mut compute: ResMut<Compute>,
+) {
+    for action in actions.read() {
+        let Action::Heat { server: name } = action else {
+            continue;
+        };
+
+        let Some(entity) = find_server(&servers, name) else {
+            rejected.send(Rejected(format!("no server named {name}")));
+            continue;
+        };
+
+        let Ok((_, mut server)) = servers.get_mut(entity) else {
+            continue;
+        };
+
+        if !server.controlled() {
+            rejected.send(Rejected(format!("{name} isn't under the virus' control")));
+            continue;
+        }
+
+        if !compute.spend(cost(action)) {
+            rejected.send(Rejected(format!("not enough compute to run that on {name}")));
+            continue;
+        }
+
+        server.program = ServerProgram::Heat;
+    }
+}
+
+// heat up servers that are running, especially the ones running the heat program or packed in
+// around coolers, and cool them down with every powered cooler in range. servers that go over
+// their `overheat` break for good.
+fn heat_servers(
+    mut servers: Query<(Entity, &mut Server, &Transform)>,
+    coolers: Query<(&Cooler, &Transform)>,
+    mut ev_overheated: EventWriter<ServerOverheated>,
+) {
+    for (entity, mut server, transform) in &mut servers {
+        let position = transform.translation.truncate();
+        let in_reach: Vec<_> = coolers
+            .iter()
+            .filter(|(cooler, cooler_transform)| {
+                let distance = cooler_transform.translation.truncate().distance(position);
+                distance <= cooler.radius * TILE_SIZE
+            })
+            .map(|(cooler, _)| cooler)
+            .collect();
+
+        let mut heat = -COOLING_RATE;
+        if !matches!(server.state, ServerState::Off | ServerState::Broken) {
+            heat += IDLE_HEAT;
+            if server.program == ServerProgram::Heat {
+                heat += HEAT_RATE;
+            }
+            if !in_reach.is_empty() {
+                heat += RACK_HEAT;
+            }
+        }
+        heat -= in_reach
+            .iter()
+            .filter(|cooler| cooler.on())
+            .map(|cooler| cooler.cooling)
+            .sum::<f32>();
+        server.temp = (server.temp + heat).max(0.0);
+
+        if server.temp > server.overheat && server.state != ServerState::Broken {
+            let virus_killed = server.state == ServerState::Infected;
+            server.state = ServerState::Broken;
+            server.program = ServerProgram::Wait;
+            ev_overheated.send(ServerOverheated {
+                server: entity,
+                virus_killed,
+            });
+        }
+    }
+}
+
+// tell the player about servers that have overheated.
+fn report_overheating(
+    mut ev_overheated: EventReader<ServerOverheated>,
+    mut notices: EventWriter<Notice>,
+    servers: Query<&Server>,
+) {
+    for ev in ev_overheated.read() {
+        let server = name_of(&servers, ev.server);
+        notices.send(Notice(if ev.virus_killed {
+            format!("{server} overheated, taking the virus running on it down with it")
+        } else {
+            format!("{server} overheated and broke down")
+        }));
+    }
+}
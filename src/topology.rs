@@ -0,0 +1,332 @@
+use std::collections::{HashSet, VecDeque};
+
+use rand::{seq::IteratorRandom, Rng};
+
+use crate::level::{Blueprint, NodeKind};
+
+// the shape of the network a level is built around.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Topology {
+    // each pair of servers is linked with a chance of `link_chance`.
+    Random { servers: usize, link_chance: f64 },
+    // every server is linked to the next, and the last back to the first.
+    Ring { servers: usize },
+    // every leaf is linked to a core switch in the middle, and nothing else.
+    Star { leaves: usize },
+    // a root with `branching` children, each with `branching` children of their own,
+    // `depth` levels down.
+    Tree { depth: usize, branching: usize },
+    // every server is linked to every other server.
+    Mesh { servers: usize },
+    // racks of servers, each linked to the top-of-rack switch for their rack, and every
+    // top-of-rack switch linked to every spine switch.
+    Datacenter {
+        spines: usize,
+        racks: usize,
+        rack_size: usize,
+    },
+}
+
+impl Topology {
+    // a preset of each shape, at a size that fits on the screen.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "random" => Some(Topology::default()),
+            "ring" => Some(Topology::Ring { servers: 8 }),
+            "star" => Some(Topology::Star { leaves: 7 }),
+            "tree" => Some(Topology::Tree {
+                depth: 2,
+                branching: 2,
+            }),
+            "mesh" => Some(Topology::Mesh { servers: 5 }),
+            "datacenter" => Some(Topology::Datacenter {
+                spines: 2,
+                racks: 3,
+                rack_size: 2,
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Topology {
+    fn default() -> Self {
+        Topology::Random {
+            servers: 8,
+            link_chance: 0.25,
+        }
+    }
+}
+
+// lay out the servers and links for a topology, and power them from `generators` generators.
+pub fn build(topology: &Topology, generators: usize, rng: &mut impl Rng) -> Blueprint {
+    let mut blueprint = Blueprint::default();
+    // every node in `servers` gets power, switches included.
+    let servers = match *topology {
+        Topology::Random {
+            servers,
+            link_chance,
+        } => {
+            let servers = add_servers(&mut blueprint, servers);
+            for (i, &a) in servers.iter().enumerate() {
+                for &b in &servers[i + 1..] {
+                    if rng.gen_bool(link_chance) {
+                        blueprint.link(a, b);
+                    }
+                }
+            }
+            connect(&mut blueprint, &servers, rng);
+            servers
+        }
+        Topology::Ring { servers } => {
+            let servers = add_servers(&mut blueprint, servers);
+            for (i, &a) in servers.iter().enumerate() {
+                let b = servers[(i + 1) % servers.len()];
+                if a != b && !(servers.len() == 2 && i == 1) {
+                    blueprint.link(a, b);
+                }
+            }
+            servers
+        }
+        Topology::Star { leaves } => {
+            let mut servers = add_servers(&mut blueprint, leaves);
+            let core = blueprint.add(NodeKind::Switch);
+            for &leaf in &servers {
+                blueprint.link(core, leaf);
+            }
+            servers.push(core);
+            servers
+        }
+        Topology::Tree { depth, branching } => {
+            let root = blueprint.add(NodeKind::Server);
+            let mut servers = vec![root];
+            let mut level = vec![root];
+            for _ in 0..depth {
+                let mut next = vec![];
+                for &parent in &level {
+                    for child in add_servers(&mut blueprint, branching) {
+                        blueprint.link(parent, child);
+                        next.push(child);
+                    }
+                }
+                servers.extend(&next);
+                level = next;
+            }
+            // the virus starts out at the bottom of the tree.
+            blueprint.start = level.last().copied();
+            servers
+        }
+        Topology::Mesh { servers } => {
+            let servers = add_servers(&mut blueprint, servers);
+            for (i, &a) in servers.iter().enumerate() {
+                for &b in &servers[i + 1..] {
+                    blueprint.link(a, b);
+                }
+            }
+            servers
+        }
+        Topology::Datacenter {
+            spines,
+            racks,
+            rack_size,
+        } => {
+            let mut servers = vec![];
+            let spines: Vec<_> = (0..spines)
+                .map(|_| blueprint.add(NodeKind::Switch))
+                .collect();
+            for _ in 0..racks {
+                let top = blueprint.add(NodeKind::Switch);
+                for &spine in &spines {
+                    blueprint.link(top, spine);
+                }
+                servers.push(top);
+                for server in add_servers(&mut blueprint, rack_size) {
+                    blueprint.link(top, server);
+                    servers.push(server);
+                }
+            }
+            // the virus starts out in the last rack.
+            blueprint.start = servers.last().copied().filter(|_| rack_size > 0);
+            servers.extend(spines);
+            servers
+        }
+    };
+
+    power(&mut blueprint, &servers, generators);
+    blueprint
+}
+
+fn add_servers(blueprint: &mut Blueprint, count: usize) -> Vec<usize> {
+    (0..count)
+        .map(|_| blueprint.add(NodeKind::Server))
+        .collect()
+}
+
+// link each group of servers that can't reach the first server to a random server that can,
+// so the virus can get everywhere.
+fn connect(blueprint: &mut Blueprint, servers: &[usize], rng: &mut impl Rng) {
+    let Some(&first) = servers.first() else {
+        return;
+    };
+    let mut reached = reachable(blueprint, first);
+    for &server in servers {
+        if !reached.contains(&server) {
+            let linked = servers
+                .iter()
+                .copied()
+                .filter(|s| reached.contains(s))
+                .choose(rng)
+                .unwrap_or(first);
+            blueprint.link(linked, server);
+            reached.extend(reachable(blueprint, server));
+        }
+    }
+}
+
+// every node that can be reached from `from` along links.
+fn reachable(blueprint: &Blueprint, from: usize) -> HashSet<usize> {
+    let mut reached = HashSet::from([from]);
+    let mut queue = VecDeque::from([from]);
+    while let Some(node) = queue.pop_front() {
+        for &(a, b) in &blueprint.links {
+            let next = if a == node {
+                b
+            } else if b == node {
+                a
+            } else {
+                continue;
+            };
+            if reached.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+    reached
+}
+
+// split the nodes into runs, in the order they're given, and power each run from its own
+// generator. nodes next to each other in a topology tend to share a generator.
+fn power(blueprint: &mut Blueprint, servers: &[usize], generators: usize) {
+    if generators == 0 {
+        return;
+    }
+    let generators: Vec<_> = (0..generators)
+        .map(|_| blueprint.add(NodeKind::Generator))
+        .collect();
+    for (i, &server) in servers.iter().enumerate() {
+        blueprint.link(server, generators[i * generators.len() / servers.len()]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    fn build_unpowered(topology: Topology) -> Blueprint {
+        build(&topology, 0, &mut StdRng::seed_from_u64(0))
+    }
+
+    #[test]
+    fn presets_have_the_links_they_describe() {
+        let links = |topology| build_unpowered(topology).links.len();
+        assert_eq!(links(Topology::Ring { servers: 5 }), 5);
+        assert_eq!(links(Topology::Ring { servers: 2 }), 1);
+        assert_eq!(links(Topology::Ring { servers: 1 }), 0);
+        assert_eq!(links(Topology::Star { leaves: 4 }), 4);
+        let tree = Topology::Tree {
+            depth: 2,
+            branching: 3,
+        };
+        assert_eq!(links(tree), 3 + 9);
+        assert_eq!(links(Topology::Mesh { servers: 4 }), 6);
+        let datacenter = Topology::Datacenter {
+            spines: 2,
+            racks: 3,
+            rack_size: 4,
+        };
+        assert_eq!(links(datacenter), 3 * 2 + 3 * 4);
+    }
+
+    #[test]
+    fn presets_start_the_virus_where_they_say() {
+        let star = build_unpowered(Topology::Star { leaves: 4 });
+        assert_eq!(star.start_server(), Some(0));
+
+        let tree = build_unpowered(Topology::Tree {
+            depth: 2,
+            branching: 2,
+        });
+        assert_eq!(tree.start, Some(6));
+
+        let datacenter = build_unpowered(Topology::Datacenter {
+            spines: 1,
+            racks: 2,
+            rack_size: 3,
+        });
+        assert_eq!(datacenter.start, Some(datacenter.nodes.len() - 1));
+        assert_eq!(datacenter.nodes[datacenter.start.unwrap()], NodeKind::Server);
+
+        let empty_racks = build_unpowered(Topology::Datacenter {
+            spines: 1,
+            racks: 2,
+            rack_size: 0,
+        });
+        assert_eq!(empty_racks.start, None);
+    }
+
+    #[test]
+    fn every_preset_has_a_name() {
+        for name in ["random", "ring", "star", "tree", "mesh", "datacenter"] {
+            assert!(Topology::from_name(name).is_some(), "no preset called {name}");
+        }
+        assert_eq!(Topology::from_name("donut"), None);
+    }
+
+    #[test]
+    fn random_networks_are_connected() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            for link_chance in [0.0, 0.1, 0.25] {
+                let topology = Topology::Random {
+                    servers: 8,
+                    link_chance,
+                };
+                let blueprint = build(&topology, 0, &mut rng);
+                assert_eq!(reachable(&blueprint, 0).len(), 8);
+            }
+        }
+    }
+
+    #[test]
+    fn power_is_shared_out_between_generators() {
+        let blueprint = build(&Topology::Ring { servers: 6 }, 3, &mut StdRng::seed_from_u64(0));
+        let generators: Vec<_> = (0..blueprint.nodes.len())
+            .filter(|&n| blueprint.nodes[n] == NodeKind::Generator)
+            .collect();
+        assert_eq!(generators.len(), 3);
+        for server in 0..6 {
+            let powered_by = blueprint
+                .links
+                .iter()
+                .filter(|&&(a, b)| a == server && generators.contains(&b))
+                .count();
+            assert_eq!(powered_by, 1, "server {server} should have one generator");
+        }
+        for generator in generators {
+            let powering = blueprint.links.iter().filter(|(_, b)| *b == generator).count();
+            assert_eq!(powering, 2);
+        }
+    }
+
+    #[test]
+    fn power_copes_with_nothing_to_power_or_nothing_to_power_it() {
+        let unpowered = build_unpowered(Topology::Mesh { servers: 3 });
+        assert_eq!(unpowered.nodes, vec![NodeKind::Server; 3]);
+
+        let empty = build(&Topology::Mesh { servers: 0 }, 2, &mut StdRng::seed_from_u64(0));
+        assert_eq!(empty.nodes, vec![NodeKind::Generator; 2]);
+        assert!(empty.links.is_empty());
+    }
+}
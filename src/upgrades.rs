@@ -0,0 +1,190 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    economy::Compute,
+    text_input::{Action, Rejected},
+};
+
+const TEXT_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
+const BACKGROUND_COLOR: Color = Color::srgb(0.15, 0.15, 0.15);
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<Upgrades>()
+        .add_systems(Startup, setup_upgrade_panel)
+        .add_systems(Update, (buy_upgrades, update_upgrade_panel).chain());
+}
+
+// abilities the virus can improve with `upd <upgrade>`.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Upgrade {
+    // hacks finish faster.
+    Hack,
+    // heating servers draws less of the defender's attention.
+    Heat,
+    // servers wired to a controlled server can be cycled without hacking them first.
+    Cycle,
+    // the virus can fork into another instance for each level.
+    Fork,
+}
+
+impl Upgrade {
+    pub const ALL: [Upgrade; 4] = [Upgrade::Hack, Upgrade::Heat, Upgrade::Cycle, Upgrade::Fork];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Upgrade::Hack => "hack",
+            Upgrade::Heat => "heat",
+            Upgrade::Cycle => "cycle",
+            Upgrade::Fork => "fork",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Upgrade> {
+        Upgrade::ALL.into_iter().find(|upgrade| upgrade.name() == name)
+    }
+
+    pub fn max_level(self) -> usize {
+        match self {
+            Upgrade::Hack => 3,
+            Upgrade::Heat => 2,
+            Upgrade::Cycle => 1,
+            Upgrade::Fork => 3,
+        }
+    }
+
+    // the upgrade and level that has to be bought before this one is unlocked.
+    pub fn requires(self) -> Option<(Upgrade, usize)> {
+        match self {
+            Upgrade::Hack => None,
+            Upgrade::Heat => Some((Upgrade::Hack, 1)),
+            Upgrade::Cycle => Some((Upgrade::Heat, 1)),
+            Upgrade::Fork => Some((Upgrade::Hack, 2)),
+        }
+    }
+
+    // compute needed to go from `level` to the next level.
+    pub fn cost(self, level: usize) -> f32 {
+        let base = match self {
+            Upgrade::Hack => 40.0,
+            Upgrade::Heat => 60.0,
+            Upgrade::Cycle => 120.0,
+            Upgrade::Fork => 80.0,
+        };
+        base * (level + 1) as f32
+    }
+}
+
+// levels bought so far. these last for the whole run, not just the current level.
+#[derive(Resource, Default, Clone, PartialEq)]
+pub struct Upgrades(HashMap<Upgrade, usize>);
+
+impl Upgrades {
+    pub fn level(&self, upgrade: Upgrade) -> usize {
+        self.0.get(&upgrade).copied().unwrap_or(0)
+    }
+
+    pub fn unlocked(&self, upgrade: Upgrade) -> bool {
+        upgrade
+            .requires()
+            .is_none_or(|(required, level)| self.level(required) >= level)
+    }
+}
+
+#[derive(Component)]
+struct UpgradePanel;
+
+fn setup_upgrade_panel(mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                right: Val::Px(20.0),
+                top: Val::Px(20.0),
+                padding: UiRect::all(Val::Px(4.0)),
+                ..default()
+            },
+            background_color: BACKGROUND_COLOR.into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 16.,
+                        color: TEXT_COLOR,
+                        ..default()
+                    },
+                ),
+                UpgradePanel,
+            ));
+        });
+}
+
+// run `upd <upgrade>` commands.
+fn buy_upgrades(
+    mut actions: EventReader<Action>,
+    mut rejected: EventWriter<Rejected>,
+    mut upgrades: ResMut<Upgrades>,
+    mut compute: ResMut<Compute>,
+) {
+    for action in actions.read() {
+        let Action::Upgrade { upgrade } = action else {
+            continue;
+        };
+        let upgrade = *upgrade;
+
+        let level = upgrades.level(upgrade);
+        if level >= upgrade.max_level() {
+            rejected.send(Rejected(format!("{} is fully upgraded", upgrade.name())));
+            continue;
+        }
+
+        if !upgrades.unlocked(upgrade) {
+            rejected.send(Rejected(format!("{} is still locked", upgrade.name())));
+            continue;
+        }
+
+        if !compute.spend(upgrade.cost(level)) {
+            rejected.send(Rejected(format!(
+                "not enough compute to upgrade {}",
+                upgrade.name()
+            )));
+            continue;
+        }
+
+        upgrades.0.insert(upgrade, level + 1);
+    }
+}
+
+fn update_upgrade_panel(
+    upgrades: Res<Upgrades>,
+    mut panel: Query<&mut Text, With<UpgradePanel>>,
+) {
+    if !upgrades.is_changed() {
+        return;
+    }
+
+    let mut lines = vec![];
+    for upgrade in Upgrade::ALL {
+        let level = upgrades.level(upgrade);
+        let status = if level >= upgrade.max_level() {
+            "maxed".to_string()
+        } else if let (false, Some((required, required_level))) =
+            (upgrades.unlocked(upgrade), upgrade.requires())
+        {
+            format!("needs {} {}", required.name(), required_level)
+        } else {
+            format!("costs {:.0}", upgrade.cost(level))
+        };
+        lines.push(format!(
+            "{} {}/{} - {}",
+            upgrade.name(),
+            level,
+            upgrade.max_level(),
+            status
+        ));
+    }
+
+    panel.single_mut().sections[0].value = lines.join("\n");
+}
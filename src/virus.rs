@@ -0,0 +1,243 @@
+use bevy::prelude::*;
+
+use crate::{
+    clock::Tick,
+    economy::{cost, Compute},
+    firewall::{passable, Firewall},
+    loading::GameState,
+    server::{find_server, relay, Server, ServerState, Switch},
+    text_input::{Action, Rejected},
+    upgrades::{Upgrade, Upgrades},
+    wire::{connected, Wire},
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_event::<Outcome>()
+        .init_resource::<LevelOutcome>()
+        .add_systems(OnEnter(GameState::Playing), reset_outcome)
+        .add_systems(Update, (move_virus, fork_virus, prune_instances).chain())
+        .add_systems(Tick, decide_outcome);
+}
+
+// how the level ended. sent once, on the tick it's decided.
+#[derive(Event, Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Outcome {
+    // every server that still works is under the virus' control.
+    Won,
+    // the virus isn't running anywhere any more.
+    Lost,
+}
+
+// how the level being played ended, once it has.
+#[derive(Resource, Default, Clone, PartialEq)]
+pub struct LevelOutcome(pub Option<Outcome>);
+
+// an instance of the virus, on the server it's running on. the virus can fork into several
+// instances, each moved and forked on its own.
+#[derive(Component)]
+pub struct Virus {
+    pub name: String,
+}
+
+// the name for a new instance, the first of v1, v2, ... that isn't taken.
+pub fn instance_name(taken: &[String]) -> String {
+    (1..)
+        .map(|n| format!("v{n}"))
+        .find(|name| !taken.contains(name))
+        .expect("there's always a free name")
+}
+
+// the server running the instance called `name`, or the only instance if no name is given.
+fn find_instance(
+    instances: &Query<(Entity, &Virus)>,
+    name: Option<&String>,
+) -> Result<Entity, String> {
+    match name {
+        Some(name) => instances
+            .iter()
+            .find(|(_, virus)| virus.name == *name)
+            .map(|(entity, _)| entity)
+            .ok_or_else(|| format!("no instance of the virus named {name}")),
+        None => match instances.iter().map(|(entity, _)| entity).collect::<Vec<_>>()[..] {
+            [entity] => Ok(entity),
+            [] => Err("the virus isn't running anywhere".to_string()),
+            _ => Err("the virus is running in more than one place, say which instance to use"
+                .to_string()),
+        },
+    }
+}
+
+// the hacked server called `name` that the virus running on `source` can reach.
+fn find_destination(
+    servers: &Query<(Entity, &mut Server)>,
+    wires: &Query<(&Wire, Option<&Firewall>)>,
+    switches: &Query<&Switch>,
+    source: Entity,
+    name: &str,
+) -> Result<Entity, String> {
+    let destination =
+        find_server(servers, name).ok_or_else(|| format!("no server named {name}"))?;
+
+    let state = servers.get(destination).ok().map(|(_, server)| server.state);
+    if state != Some(ServerState::Hacked) {
+        return Err(format!("{name} hasn't been hacked"));
+    }
+
+    let relays = |e| relay(switches, e);
+    if !connected(wires.iter().map(|(wire, _)| wire), relays, source, destination) {
+        return Err(format!("{name} isn't wired to the virus"));
+    }
+
+    let open = wires
+        .iter()
+        .filter(|(_, firewall)| passable(*firewall))
+        .map(|(wire, _)| wire);
+    if !connected(open, relays, source, destination) {
+        return Err(format!("a firewall is in the way of {name}"));
+    }
+
+    Ok(destination)
+}
+
+// move an instance of the virus from its server to a hacked server it's wired to.
+fn move_virus(
+    mut commands: Commands,
+    mut actions: EventReader<Action>,
+    mut rejected: EventWriter<Rejected>,
+    mut servers: Query<(Entity, &mut Server)>,
+    instances: Query<(Entity, &Virus)>,
+    wires: Query<(&Wire, Option<&Firewall>)>,
+    switches: Query<&Switch>,
+) {
+    for action in actions.read() {
+        let Action::Move { instance, server } = action else {
+            continue;
+        };
+
+        let moved = find_instance(&instances, instance.as_ref()).and_then(|infected| {
+            find_destination(&servers, &wires, &switches, infected, server)
+                .map(|destination| (infected, destination))
+        });
+        let (infected, destination) = match moved {
+            Ok(moved) => moved,
+            Err(reason) => {
+                rejected.send(Rejected(reason));
+                continue;
+            }
+        };
+
+        if let Ok((_, mut server)) = servers.get_mut(infected) {
+            server.state = ServerState::Hacked;
+        }
+        if let Ok((_, mut server)) = servers.get_mut(destination) {
+            server.state = ServerState::Infected;
+        }
+        if let Ok((_, virus)) = instances.get(infected) {
+            commands.entity(infected).remove::<Virus>();
+            commands.entity(destination).insert(Virus {
+                name: virus.name.clone(),
+            });
+        }
+    }
+}
+
+// copy an instance of the virus onto a hacked server it's wired to, leaving the original
+// where it is. how many instances can run at once depends on the fork upgrade.
+fn fork_virus(
+    mut commands: Commands,
+    mut actions: EventReader<Action>,
+    mut rejected: EventWriter<Rejected>,
+    mut servers: Query<(Entity, &mut Server)>,
+    instances: Query<(Entity, &Virus)>,
+    wires: Query<(&Wire, Option<&Firewall>)>,
+    switches: Query<&Switch>,
+    upgrades: Res<Upgrades>,
+    mut compute: ResMut<Compute>,
+) {
+    // forks made this frame aren't in `instances` until their `Virus` is inserted, so keep
+    // track of every instance's name here as they're made.
+    let mut names: Vec<_> = instances.iter().map(|(_, virus)| virus.name.clone()).collect();
+    for action in actions.read() {
+        let Action::Fork { instance, server } = action else {
+            continue;
+        };
+
+        let forked = find_instance(&instances, instance.as_ref()).and_then(|infected| {
+            find_destination(&servers, &wires, &switches, infected, server)
+        });
+        let destination = match forked {
+            Ok(destination) => destination,
+            Err(reason) => {
+                rejected.send(Rejected(reason));
+                continue;
+            }
+        };
+
+        let limit = max_instances(&upgrades);
+        if names.len() >= limit {
+            rejected.send(Rejected(format!(
+                "the virus can't run more than {limit} instances at once"
+            )));
+            continue;
+        }
+
+        if !compute.spend(cost(action)) {
+            rejected.send(Rejected(format!("not enough compute to fork onto {server}")));
+            continue;
+        }
+
+        if let Ok((_, mut server)) = servers.get_mut(destination) {
+            server.state = ServerState::Infected;
+        }
+        let name = instance_name(&names);
+        names.push(name.clone());
+        commands.entity(destination).insert(Virus { name });
+    }
+}
+
+// how many instances of the virus can run at once.
+pub fn max_instances(upgrades: &Upgrades) -> usize {
+    1 + upgrades.level(Upgrade::Fork)
+}
+
+// forget instances that have been killed, by power loss, overheating or anything else.
+fn prune_instances(mut commands: Commands, instances: Query<(Entity, &Virus, &Server)>) {
+    for (entity, _, server) in &instances {
+        if server.state != ServerState::Infected {
+            commands.entity(entity).remove::<Virus>();
+        }
+    }
+}
+
+fn reset_outcome(mut outcome: ResMut<LevelOutcome>) {
+    outcome.0 = None;
+}
+
+// end the level once the virus has taken over or been wiped out. the win and loss sounds,
+// replays and anything else that cares how a level ended all go by the `Outcome` sent here.
+fn decide_outcome(
+    servers: Query<&Server>,
+    mut ev_outcome: EventWriter<Outcome>,
+    mut decided: ResMut<LevelOutcome>,
+) {
+    if decided.0.is_some() || servers.is_empty() {
+        return;
+    }
+
+    let outcome = if !servers
+        .iter()
+        .any(|server| server.state == ServerState::Infected)
+    {
+        Outcome::Lost
+    } else if servers
+        .iter()
+        .filter(|server| server.state != ServerState::Broken)
+        .all(Server::controlled)
+    {
+        Outcome::Won
+    } else {
+        return;
+    };
+    decided.0 = Some(outcome);
+    ev_outcome.send(outcome);
+}
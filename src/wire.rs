@@ -1,34 +1,206 @@
 use bevy::{
-    color::palettes::css::{BLUE, GRAY, RED},
     prelude::*,
-    ui::widget,
+    render::{
+        mesh::{Indices, MeshVertexBufferLayoutRef, PrimitiveTopology},
+        render_asset::RenderAssetUsages,
+        render_resource::{
+            AsBindGroup, RenderPipelineDescriptor, ShaderRef, SpecializedMeshPipelineError,
+        },
+    },
+    sprite::{Material2d, Material2dKey, Material2dPlugin, MaterialMesh2dBundle, Mesh2dHandle},
     utils::HashSet,
 };
 use itertools::Itertools;
 
-use crate::server::{Generator, Server};
+use crate::{
+    economy::{cost, Compute},
+    firewall::Firewall,
+    server::{find_cooler, find_server, find_switch, Battery, Cooler, Generator, Server, Switch},
+    text_input::{Action, Rejected},
+    TILE_SIZE,
+};
 
 const WIRE_Z: f32 = 0.0;
 
+// power wires are drawn under communication wires, and both under servers.
+const POWER_WIRE_Z: f32 = -0.5;
+const COMMUNICATION_WIRE_Z: f32 = -0.4;
+
+// how fast power pulses travel along a wire, in pixels per second.
+const FLOW_SPEED: f32 = 24.0;
+
+// fraction of `overload` past which power wires start flickering.
+const FLICKER_LOAD: f32 = 0.85;
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_plugins(Material2dPlugin::<LineMaterial>::default())
+        .add_event::<UpdateWire>()
+        .add_systems(
+            Update,
+            (cut_wires, update_wires, build_wire_meshes, animate_power_flow).chain(),
+        );
+}
+
 #[derive(Component)]
 pub struct Wire {
     pub terminals: [Entity; 2],
     pub control_points: [Vec2; 2],
+    // when there are any, the wire runs straight through these points instead of along its curve.
+    pub corners: Vec<Vec2>,
     pub segments: usize,
     pub cuts: HashSet<usize>,
     pub width: f32,
 }
 
+impl Wire {
+    // points along the wire, from its first terminal at `ends[0]` to its second at `ends[1]`.
+    pub fn points(&self, ends: [Vec2; 2]) -> Vec<Vec2> {
+        if self.corners.is_empty() {
+            curve_points(ends, self.control_points, self.segments)
+        } else {
+            polyline_points(ends, &self.corners, self.segments)
+        }
+    }
+
+    // whether this wire runs between `a` and `b`, in either direction.
+    pub fn connects(&self, a: Entity, b: Entity) -> bool {
+        self.terminals == [a, b] || self.terminals == [b, a]
+    }
+}
+
+// sample a bezier curve between `ends`. control points are given relative to the ends: x runs
+// from one end to the other, and y sideways, in multiples of the distance between them.
+pub fn curve_points(ends: [Vec2; 2], control_points: [Vec2; 2], segments: usize) -> Vec<Vec2> {
+    let diff = ends[1] - ends[0];
+    let perpendicular = Vec2::new(0., 1.).rotate(diff);
+    let control_points = control_points.map(|pt| ends[0] + diff * pt.x + perpendicular * pt.y);
+    CubicBezier::new(vec![[ends[0], control_points[0], control_points[1], ends[1]]])
+        .to_curve()
+        .iter_positions(segments)
+        .collect()
+}
+
+// sample straight lines from `ends[0]` through each of `corners` to `ends[1]`, sharing
+// about `segments` pieces between them by length. every corner is kept exactly.
+pub fn polyline_points(ends: [Vec2; 2], corners: &[Vec2], segments: usize) -> Vec<Vec2> {
+    let path = [&[ends[0]], corners, &[ends[1]]].concat();
+    let length: f32 = path.iter().tuple_windows().map(|(a, b)| a.distance(*b)).sum();
+
+    let mut points = vec![ends[0]];
+    for (&a, &b) in path.iter().tuple_windows() {
+        let pieces = ((segments as f32 * a.distance(b) / length.max(f32::EPSILON)).round() as usize)
+            .max(1);
+        points.extend((1..=pieces).map(|i| a.lerp(b, i as f32 / pieces as f32)));
+    }
+    points
+}
+
+// whether `a` and `b` can talk to each other, either over a wire of their own or through
+// nodes that `relays` says pass communication on, like switches that are on.
+pub fn connected<'a>(
+    wires: impl IntoIterator<Item = &'a Wire>,
+    relays: impl Fn(Entity) -> bool,
+    a: Entity,
+    b: Entity,
+) -> bool {
+    let wires = wires
+        .into_iter()
+        .filter(|wire| wire.cuts.is_empty())
+        .collect_vec();
+    let mut visited: HashSet<Entity> = [a].into_iter().collect();
+    let mut frontier = vec![a];
+    while let Some(node) = frontier.pop() {
+        for wire in &wires {
+            let other = match wire.terminals {
+                [x, y] if x == node => y,
+                [x, y] if y == node => x,
+                _ => continue,
+            };
+            if other == b {
+                return true;
+            }
+            if relays(other) && visited.insert(other) {
+                frontier.push(other);
+            }
+        }
+    }
+    false
+}
+
+// whether `a` and `b` are joined by at least one wire that hasn't been cut.
+pub fn linked<'a>(wires: impl IntoIterator<Item = &'a Wire>, a: Entity, b: Entity) -> bool {
+    wires
+        .into_iter()
+        .any(|wire| wire.cuts.is_empty() && wire.connects(a, b))
+}
+
 #[derive(Event)]
 pub struct UpdateWire {
-    operation: WireOperation,
-    wire: Entity,
-    pos: usize,
+    pub operation: WireOperation,
+    pub wire: Entity,
+    pub pos: usize,
 }
 
 pub enum WireOperation {
     Cut,
-    Join,
+}
+
+// how a wire is lit. power wires pulse from their generator with its load,
+// everything else just shows its vertex colours.
+#[derive(Debug, Clone, Default)]
+pub struct LineFlow {
+    // 0 to 1, how brightly the wire is lit, and how strong its pulses are.
+    pub intensity: f32,
+    // 0 to 1, how often the wire drops out.
+    pub flicker: f32,
+    // how fast pulses move along the wire. pulses stop when this is 0.
+    pub speed: f32,
+    // offsets the flicker so that wires don't all flicker together.
+    pub seed: f32,
+}
+
+// draws wire meshes built from triangles, coloured per vertex. the flow goes to the shader
+// packed into a single vec4, in the order of `LineFlow`'s fields.
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone, Default)]
+#[uniform(0, Vec4)]
+pub struct LineMaterial {
+    pub flow: LineFlow,
+}
+
+impl From<&LineMaterial> for Vec4 {
+    fn from(material: &LineMaterial) -> Self {
+        let LineFlow {
+            intensity,
+            flicker,
+            speed,
+            seed,
+        } = material.flow;
+        Vec4::new(intensity, flicker, speed, seed)
+    }
+}
+
+impl Material2d for LineMaterial {
+    fn vertex_shader() -> ShaderRef {
+        "shaders/line_material.wgsl".into()
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        "shaders/line_material.wgsl".into()
+    }
+
+    fn specialize(
+        descriptor: &mut RenderPipelineDescriptor,
+        layout: &MeshVertexBufferLayoutRef,
+        _key: Material2dKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        let vertex_layout = layout.0.get_layout(&[
+            Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
+            Mesh::ATTRIBUTE_COLOR.at_shader_location(1),
+            Mesh::ATTRIBUTE_UV_0.at_shader_location(2),
+        ])?;
+        descriptor.vertex.buffers = vec![vertex_layout];
+        Ok(())
+    }
 }
 
 // get the perpendicular of the provided vector.
@@ -36,144 +208,317 @@ fn perp(v: Vec2) -> Vec2 {
     Vec2::new(-v.y, v.x)
 }
 
-// update wire meshes when we get a cut/join event.
-fn update_wires(
-    mut ev_wireupdate: EventReader<UpdateWire>,
-    mut wires: Query<(Entity, &mut Wire)>,
-    servers: Query<(Entity, &Transform), With<Server>>,
-    generators: Query<(Entity, &Transform), With<Generator>>,
+// run `msg <server> cut <node>` commands, cutting the wire between a server the virus
+// controls and a server or switch it's wired to. a server can also cut the power to a
+// cooler it's close enough to be cooled by, or with `msg <server> cut <switch> power`, to a
+// switch it's wired to.
+fn cut_wires(
+    mut actions: EventReader<Action>,
+    mut rejected: EventWriter<Rejected>,
+    mut ev_wireupdate: EventWriter<UpdateWire>,
+    servers: Query<(Entity, &Server)>,
+    switches: Query<(Entity, &Switch)>,
+    coolers: Query<(Entity, &Cooler)>,
+    transforms: Query<&Transform>,
+    wires: Query<(Entity, &Wire)>,
+    mut compute: ResMut<Compute>,
 ) {
-    for ev in ev_wireupdate.read() {
-        if let Some((_, mut wire)) = wires.iter_mut().filter(|(e, _)| *e == ev.wire).next() {
-            match ev.operation {
-                WireOperation::Cut => {
-                    wire.cuts.replace(ev.pos);
+    for action in actions.read() {
+        let Action::Cut {
+            server,
+            target,
+            power,
+        } = action
+        else {
+            continue;
+        };
+
+        let Some(source) = find_server(&servers, server) else {
+            rejected.send(Rejected(format!("no server named {server}")));
+            continue;
+        };
+        let Some(other) = find_server(&servers, target)
+            .or_else(|| find_switch(&switches, target))
+            .or_else(|| find_cooler(&coolers, target))
+        else {
+            rejected.send(Rejected(format!("no server, switch or cooler named {target}")));
+            continue;
+        };
+
+        if !servers.get(source).is_ok_and(|(_, server)| server.controlled()) {
+            rejected.send(Rejected(format!("{server} isn't under the virus' control")));
+            continue;
+        }
+
+        let wire = match (coolers.get(other), switches.get(other)) {
+            (_, Ok((_, switch))) if *power => {
+                let adjacent = wires
+                    .iter()
+                    .any(|(_, wire)| wire.cuts.is_empty() && wire.connects(source, other));
+                if !adjacent {
+                    rejected.send(Rejected(format!("{server} isn't wired to {target}")));
+                    continue;
                 }
-                WireOperation::Join => {
-                    wire.cuts.remove(&ev.pos);
+                let Some(supply) = switch.supply else {
+                    rejected.send(Rejected(format!("{target} has no power to cut")));
+                    continue;
+                };
+                // only the link it's drawing from. any others take over once it's cut.
+                wires
+                    .iter()
+                    .find(|(_, wire)| wire.cuts.is_empty() && wire.connects(other, supply))
+            }
+            _ if *power => {
+                rejected.send(Rejected(format!("{target} isn't a switch")));
+                continue;
+            }
+            (Ok((_, cooler)), _) => {
+                let in_range = transforms.get_many([source, other]).is_ok_and(|[a, b]| {
+                    let distance = a.translation.truncate().distance(b.translation.truncate());
+                    distance <= cooler.radius * TILE_SIZE
+                });
+                if !in_range {
+                    rejected.send(Rejected(format!("{server} isn't close enough to {target}")));
+                    continue;
                 }
+                // coolers are only wired to their power.
+                wires
+                    .iter()
+                    .find(|(_, wire)| wire.cuts.is_empty() && wire.terminals.contains(&other))
             }
+            (Err(_), _) => wires
+                .iter()
+                .find(|(_, wire)| wire.cuts.is_empty() && wire.connects(source, other)),
+        };
+        let Some((wire, segments)) = wire.map(|(entity, wire)| (entity, wire.segments)) else {
+            rejected.send(Rejected(format!("{server} isn't wired to {target}")));
+            continue;
+        };
 
-            fn find<'a, T: Component>(
-                entity: &Entity,
-                query: &'a Query<(Entity, &Transform), With<T>>,
-            ) -> Option<&'a Transform> {
-                query
-                    .iter()
-                    .filter(|(e, _)| e == entity)
-                    .map(|(_, transform)| transform)
-                    .next()
+        if !compute.spend(cost(action)) {
+            rejected.send(Rejected(format!("not enough compute to run that on {server}")));
+            continue;
+        }
+
+        ev_wireupdate.send(UpdateWire {
+            operation: WireOperation::Cut,
+            wire,
+            pos: segments / 2,
+        });
+    }
+}
+
+// cut wires when we get a cut event. their meshes get rebuilt afterwards.
+fn update_wires(mut ev_wireupdate: EventReader<UpdateWire>, mut wires: Query<&mut Wire>) {
+    for ev in ev_wireupdate.read() {
+        let Ok(mut wire) = wires.get_mut(ev.wire) else {
+            continue;
+        };
+        match ev.operation {
+            WireOperation::Cut => {
+                wire.cuts.insert(ev.pos);
             }
+        }
+    }
+}
 
-            // wire is a powerline if either of the terminals is a generator
-            let is_pwr = wire
-                .terminals
-                .iter()
-                .any(|terminal| find(&terminal, &generators).is_some());
-
-            // get the locations of the terminals
-            let terminals = wire.terminals.map(|terminal| {
-                find(&terminal, &servers)
-                    .or(find(&terminal, &generators))
-                    .map(|transform| transform.translation.truncate())
-                    .expect("wire should be connecting server -> server or server -> generator")
+// (re)build the mesh for every wire that's new or has been cut, or had its cuts undone.
+fn build_wire_meshes(
+    mut commands: Commands,
+    wires: Query<(Entity, &Wire, Option<&Firewall>, Option<&Mesh2dHandle>), Changed<Wire>>,
+    terminals: Query<&Transform>,
+    generators: Query<(), With<Generator>>,
+    batteries: Query<(), With<Battery>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<LineMaterial>>,
+) {
+    for (entity, wire, firewall, handle) in &wires {
+        // get the locations of the terminals. anything with a position can be wired up.
+        let Ok(ends) = terminals.get_many(wire.terminals) else {
+            warn!("wire {entity:?} is missing a terminal");
+            continue;
+        };
+        let ends = ends.map(|transform| transform.translation.truncate());
+
+        // power flows out from the generator end, if there is one, or else the battery end.
+        let generator_end = wire
+            .terminals
+            .iter()
+            .position(|terminal| generators.contains(*terminal))
+            .or_else(|| {
+                wire.terminals
+                    .iter()
+                    .position(|terminal| batteries.contains(*terminal))
             });
 
-            // calculate bezier curve
-            let diff = terminals[1] - terminals[0];
-            let perpendicular = Vec2::new(0., 1.).rotate(diff);
-            let control_points = wire
-                .control_points
-                .map(|pt| terminals[0] + diff * pt.x + perpendicular * pt.y);
-            let curve = CubicBezier::new(vec![[
-                terminals[0],
-                control_points[0],
-                control_points[1],
-                terminals[1],
-            ]])
-            .to_curve();
-
-            // wire is grey if there is at least 1 cut, red if PWR, blue if COM
-            let color = if wire.cuts.len() > 0 {
-                Vec4::new(0.35, 0.35, 0.35, 1.0)
-            } else if is_pwr {
-                Vec4::new(1.0, 0.0, 0.0, 1.0)
-            } else {
-                Vec4::new(0.0, 0.2, 0.9, 1.0)
-            };
+        // wire is grey if there is at least 1 cut, red if PWR, orange if behind a firewall,
+        // blue if COM
+        let color = if !wire.cuts.is_empty() {
+            Vec4::new(0.35, 0.35, 0.35, 1.0)
+        } else if generator_end.is_some() {
+            Vec4::new(1.0, 0.0, 0.0, 1.0)
+        } else if firewall.is_some() {
+            Vec4::new(1.0, 0.55, 0.0, 1.0)
+        } else {
+            Vec4::new(0.0, 0.2, 0.9, 1.0)
+        };
 
-            // construct the lines by sampling the bezier curve, and breaking + skipping over cuts.
-            let mut lines = vec![];
-            let mut line = vec![];
-            for (i, pos) in curve.iter_positions(wire.segments).enumerate() {
-                if wire.cuts.contains(&(i + 1)) {
-                    lines.push(line);
-                    line = vec![];
-                } else if wire.cuts.contains(&(i)) {
-                    continue;
-                } else {
-                    line.push(pos);
+        let mesh = wire_mesh(wire, ends, color, generator_end == Some(1));
+        let z = if generator_end.is_some() {
+            POWER_WIRE_Z
+        } else {
+            COMMUNICATION_WIRE_Z
+        };
+        match handle {
+            Some(handle) => {
+                if let Some(existing) = meshes.get_mut(&handle.0) {
+                    *existing = mesh;
                 }
             }
-            lines.push(line);
-
-            // construct triangles - wgpu cannot draw lines portably. mucho sado :(
-            let mut verts = vec![];
-            let mut breaks = HashSet::new();
-            for line in lines {
-                let mut prev = line[0];
-                let mut line = line
-                    .iter()
-                    .tuple_windows()
-                    .flat_map(|(&a, &b)| {
-                        let normal = (perp(a - prev) + perp(b - a)).normalize();
-                        prev = a.clone();
-                        [a + normal * wire.width, a - normal * wire.width]
-                    })
-                    .collect_vec();
-
-                let normal = perp(line[line.len() - 1] - line[line.len() - 2]);
-                line.push(line[line.len() - 1] + normal * wire.width);
-                line.push(line[line.len() - 1] - normal * wire.width);
-                verts.extend(line.into_iter().map(|pt| Vec3::new(pt.x, pt.y, WIRE_Z)));
-                breaks.insert(verts.len());
+            None => {
+                commands.entity(entity).insert(MaterialMesh2dBundle {
+                    mesh: Mesh2dHandle(meshes.add(mesh)),
+                    material: materials.add(LineMaterial {
+                        flow: LineFlow {
+                            intensity: 1.0,
+                            seed: entity.index() as f32,
+                            ..default()
+                        },
+                    }),
+                    transform: Transform::from_xyz(0.0, 0.0, z),
+                    ..default()
+                });
             }
+        }
+    }
+}
 
-            let indices = (0..verts.len())
-                .filter(|i| i % 2 == 0)
-                .filter(|i| !breaks.contains(&(i + 2)))
-                .flat_map(|i| [i, i + 3, i + 1, i, i + 2, i + 3]);
+// sample the wire and turn it into a strip of triangles, skipping over cuts.
+// `reversed` measures distance along the wire from its second terminal rather than its first.
+fn wire_mesh(wire: &Wire, ends: [Vec2; 2], color: Vec4, reversed: bool) -> Mesh {
+    let points = wire.points(ends);
+
+    // how far along the wire each sample is, so that the shader can animate along it.
+    let mut distances = vec![0.0];
+    for (a, b) in points.iter().tuple_windows() {
+        distances.push(distances[distances.len() - 1] + a.distance(*b));
+    }
+    if reversed {
+        let length = distances[distances.len() - 1];
+        distances.iter_mut().for_each(|distance| *distance = length - *distance);
+    }
+
+    // construct the lines by breaking + skipping over cuts.
+    let mut lines = vec![];
+    let mut line = vec![];
+    for (i, (pos, distance)) in points.into_iter().zip(distances).enumerate() {
+        if wire.cuts.contains(&(i + 1)) {
+            lines.push(std::mem::take(&mut line));
+        } else if wire.cuts.contains(&i) {
+            continue;
+        } else {
+            line.push((pos, distance));
         }
     }
+    lines.push(line);
+
+    // construct triangles - wgpu cannot draw lines portably. mucho sado :(
+    let mut positions = vec![];
+    let mut uvs = vec![];
+    let mut indices = vec![];
+    for line in lines.into_iter().filter(|line| line.len() >= 2) {
+        let start = positions.len() as u32;
+        for i in 0..line.len() {
+            let before = line[i.saturating_sub(1)].0;
+            let after = line[(i + 1).min(line.len() - 1)].0;
+            let normal = perp(after - before).normalize_or_zero();
+            let (pos, distance) = line[i];
+            for side in [pos + normal * wire.width, pos - normal * wire.width] {
+                positions.push([side.x, side.y, WIRE_Z]);
+                uvs.push([distance, 0.0]);
+            }
+        }
+        for i in (start..positions.len() as u32 - 2).step_by(2) {
+            indices.extend([i, i + 3, i + 1, i, i + 2, i + 3]);
+        }
+    }
+    let colors = vec![color.to_array(); positions.len()];
+
+    Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::RENDER_WORLD)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+        .with_inserted_indices(Indices::U32(indices))
 }
 
-pub struct Lines {
-    geometry: Vec<Vec<(Vec3, Vec4)>>,
-    width: f32,
-}
-
-impl Lines {
-    pub fn mesh(&self) -> Mesh {}
-}
-
-pub fn setup_env(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>) {
-    commands.spawn(MaterialMeshBundle {
-        mesh: meshes.add(LineList {
-            lines: vec![
-                (
-                    Vec3::ZERO,
-                    Vec3::new(100.0, 1.0, 0.0),
-                    Vec4::new(1.0, 0.0, 0.0, 1.0),
-                ),
-                (
-                    Vec3::new(1.0, 10.0, 0.0),
-                    Vec3::new(1.0, 0.0, 0.0),
-                    Vec4::new(0.0, 1.0, 0.0, 1.0),
-                ),
-            ],
-        }),
-        transform: Transform::from_xyz(0.0, 0.0, 0.0),
-        material: materials.add(LineMaterial {}),
-        ..default()
-    });
+// light up power wires with how hard their generator is working. power only flows down a wire
+// while the node on the other end is drawing from that generator. wires out of a battery light
+// up with how much charge it has left, and flicker as it runs low.
+fn animate_power_flow(
+    wires: Query<(&Wire, &Handle<LineMaterial>)>,
+    generators: Query<&Generator>,
+    batteries: Query<&Battery>,
+    servers: Query<&Server>,
+    switches: Query<&Switch>,
+    coolers: Query<&Cooler>,
+    mut materials: ResMut<Assets<LineMaterial>>,
+) {
+    // what a node draws its power from, for anything that draws power.
+    let supply = |node: Entity| {
+        servers
+            .get(node)
+            .map(|server| server.supply)
+            .or_else(|_| batteries.get(node).map(|battery| battery.grid))
+            .or_else(|_| switches.get(node).map(|switch| switch.supply))
+            .or_else(|_| coolers.get(node).map(|cooler| cooler.supply))
+            .ok()
+    };
+    let load = |generator: &Generator| (generator.load / generator.overload).clamp(0.0, 1.0);
+
+    for (wire, handle) in &wires {
+        let power = match wire.terminals {
+            [a, b] | [b, a] if generators.contains(a) => generators
+                .get(a)
+                .ok()
+                .zip(supply(b))
+                .map(|(generator, supply)| (load(generator), load(generator), supply == Some(a))),
+            [a, b] | [b, a] if batteries.contains(a) => batteries
+                .get(a)
+                .ok()
+                .zip(servers.get(b).ok())
+                .map(|(battery, server)| {
+                    let charge = (battery.charge / battery.capacity).clamp(0.0, 1.0);
+                    (charge, 1.0 - charge, server.supply == Some(a))
+                }),
+            _ => None,
+        };
+        // how brightly the wire is lit, and how close it is to giving out.
+        let Some((intensity, strain, supplying)) = power else {
+            continue;
+        };
+
+        let flow = if supplying && wire.cuts.is_empty() {
+            let flicker = ((strain - FLICKER_LOAD) / (1.0 - FLICKER_LOAD)).max(0.0);
+            (intensity, FLOW_SPEED, flicker)
+        } else {
+            (0.0, 0.0, 0.0)
+        };
+
+        // getting the material mutably marks it changed, and it's sent to the gpu again.
+        let unchanged = materials.get(handle).is_some_and(|material| {
+            let LineFlow {
+                intensity,
+                speed,
+                flicker,
+                ..
+            } = material.flow;
+            (intensity, speed, flicker) == flow
+        });
+        if unchanged {
+            continue;
+        }
+        if let Some(material) = materials.get_mut(handle) {
+            (material.flow.intensity, material.flow.speed, material.flow.flicker) = flow;
+        }
+    }
 }